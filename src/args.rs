@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cmd::CommandLine;
+use crate::cmd::Line;
+use crate::engine::DoomEngine;
+use crate::engine::DoomEngineKind;
+use crate::error::Error;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum Opt {
+    Complevel,
+    PistolStart,
+    VideoMode,
+    Geometry,
+    Skill,
+    Warp,
+    Record,
+    LongTics,
+    ShortTics,
+    RecordFromTo,
+    PlayDemo,
+    TimeDemo,
+    VidDump,
+    NoMonsters,
+    Fast,
+    Respawn,
+}
+
+impl Display for Opt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Complevel => "--compatibility-level",
+            Self::PistolStart => "--pistol-start",
+            Self::VideoMode => "--video-mode",
+            Self::Geometry => "--geometry",
+            Self::Skill => "--skill",
+            Self::Warp => "--warp",
+            Self::Record => "--record",
+            Self::LongTics => "long tics",
+            Self::ShortTics => "--short-tics",
+            Self::RecordFromTo => "--record-from-to",
+            Self::PlayDemo => "--play-demo",
+            Self::TimeDemo => "timing demos",
+            Self::VidDump => "--render",
+            Self::NoMonsters => "--no-monsters",
+            Self::Fast => "--fast",
+            Self::Respawn => "--respawn",
+        })
+    }
+}
+
+pub(crate) type ArgTable = HashMap<Opt, Option<Vec<String>>>;
+
+fn words(words: &[&str]) -> Option<Vec<String>> {
+    Some(words.iter().map(|w| w.to_string()).collect())
+}
+
+fn default_table(kind: DoomEngineKind) -> ArgTable {
+    use Opt::*;
+    let common = [
+        (Skill, words(&["-skill", "{value}"])),
+        (Warp, words(&["-warp", "{episode}", "{map}"])),
+        (Record, words(&["-record", "{value}"])),
+        (PlayDemo, words(&["-playdemo", "{value}"])),
+        (TimeDemo, words(&["-timedemo", "{value}"])),
+        (NoMonsters, words(&["-nomonsters"])),
+        (Fast, words(&["-fast"])),
+        (Respawn, words(&["-respawn"])),
+    ];
+    let specific = match kind {
+        DoomEngineKind::Boom | DoomEngineKind::MBF => vec![
+            (Complevel, words(&["-complevel", "{value}"])),
+            (PistolStart, words(&["-pistolstart"])),
+            (VideoMode, words(&["-vidmode", "{value}"])),
+            (Geometry, words(&["-geom", "{value}"])),
+            (LongTics, words(&["-longtics"])),
+            (ShortTics, words(&["-shorttics"])),
+            (RecordFromTo, words(&["-recordfromto", "{from}", "{to}"])),
+            (VidDump, words(&["-viddump", "{value}"])),
+        ],
+        DoomEngineKind::Vanilla => vec![
+            (Complevel, words(&["-gameversion", "{gameversion}"])),
+            (Geometry, words(&["-geometry", "{value}"])),
+            (LongTics, words(&["-longtics"])),
+        ],
+        DoomEngineKind::Eternity => vec![(Geometry, words(&["-geom", "{value}"]))],
+        DoomEngineKind::ZDoom => vec![
+            (Skill, words(&["+skill", "{value}"])),
+            (Warp, words(&["+map", "{name}"])),
+            (
+                Geometry,
+                words(&["-width", "{width}", "-height", "{height}"]),
+            ),
+        ],
+    };
+    common.into_iter().chain(specific).collect()
+}
+
+pub(crate) struct EngineArgs {
+    engine: String,
+    table: ArgTable,
+}
+
+impl EngineArgs {
+    pub fn new(name: &str, engine: &DoomEngine) -> Self {
+        let mut table = default_table(engine.kind);
+        table.extend(engine.args.clone());
+        Self {
+            engine: name.to_owned(),
+            table,
+        }
+    }
+
+    pub fn supports(&self, opt: Opt) -> bool {
+        matches!(self.table.get(&opt), Some(Some(_)))
+    }
+
+    pub fn translate(
+        &self,
+        opt: Opt,
+        values: &[(&str, impl AsRef<str>)],
+    ) -> Result<Vec<String>, Error> {
+        let template = match self.table.get(&opt) {
+            Some(Some(template)) => template,
+            _ => {
+                return Err(Error::UnsupportedOption {
+                    engine: self.engine.clone(),
+                    option: opt.to_string(),
+                })
+            }
+        };
+        let mut args = vec![];
+        for word in template {
+            let mut word = word.clone();
+            let mut from = 0;
+            while let Some(start) = word[from..].find('{').map(|i| from + i) {
+                let end = match word[start..].find('}') {
+                    Some(n) => start + n,
+                    None => break,
+                };
+                let name = &word[start + 1..end];
+                let value = values
+                    .iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, value)| value.as_ref())
+                    .ok_or_else(|| Error::MissingValue {
+                        engine: self.engine.clone(),
+                        option: opt.to_string(),
+                        name: name.to_owned(),
+                    })?;
+                word.replace_range(start..=end, value);
+                from = start + value.len();
+            }
+            if !word.is_empty() {
+                args.push(word);
+            }
+        }
+        Ok(args)
+    }
+
+    pub fn push(
+        &self,
+        cmdline: &mut CommandLine,
+        opt: Opt,
+        values: &[(&str, impl AsRef<str>)],
+    ) -> Result<(), Error> {
+        let args = self.translate(opt, values)?;
+        if !args.is_empty() {
+            cmdline.push_line(Line::from_words(&args, 1));
+        }
+        Ok(())
+    }
+
+    pub fn push_flag(&self, cmdline: &mut CommandLine, opt: Opt) -> Result<(), Error> {
+        self.push(cmdline, opt, &[] as &[(&str, &str)])
+    }
+}
+
+pub(crate) fn geometry_values(geom: &str) -> Vec<(&'static str, String)> {
+    let mut values = vec![("value", geom.to_owned())];
+    if let Some((width, height)) = geom.split_once(['x', 'X']) {
+        values.push(("width", width.to_owned()));
+        values.push(("height", height.trim_end_matches(['w', 'f']).to_owned()));
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complevel::complevel_values;
+    use crate::maps::warp_values;
+    use crate::skill::skill_values;
+
+    fn engine_args(kind: DoomEngineKind) -> EngineArgs {
+        EngineArgs {
+            engine: "test".to_owned(),
+            table: default_table(kind),
+        }
+    }
+
+    #[test]
+    fn boom() {
+        let args = engine_args(DoomEngineKind::Boom);
+        assert_eq!(
+            args.translate(Opt::Warp, &warp_values("E1M2")).unwrap(),
+            ["-warp", "1", "2"]
+        );
+        assert_eq!(
+            args.translate(Opt::Warp, &warp_values("MAP07")).unwrap(),
+            ["-warp", "7"]
+        );
+        assert_eq!(
+            args.translate(Opt::Complevel, &complevel_values(9))
+                .unwrap(),
+            ["-complevel", "9"]
+        );
+        assert_eq!(
+            args.translate(Opt::RecordFromTo, &[("from", "a.lmp"), ("to", "b.lmp")])
+                .unwrap(),
+            ["-recordfromto", "a.lmp", "b.lmp"]
+        );
+        assert!(args.supports(Opt::PistolStart));
+    }
+
+    #[test]
+    fn vanilla() {
+        let args = engine_args(DoomEngineKind::Vanilla);
+        assert_eq!(
+            args.translate(Opt::Complevel, &complevel_values(2))
+                .unwrap(),
+            ["-gameversion", "1.9"]
+        );
+        assert_eq!(
+            args.translate(Opt::Skill, &skill_values(4, DoomEngineKind::Vanilla))
+                .unwrap(),
+            ["-skill", "4"]
+        );
+        assert!(matches!(
+            args.translate(Opt::PistolStart, &[] as &[(&str, &str)]),
+            Err(Error::UnsupportedOption { .. })
+        ));
+    }
+
+    #[test]
+    fn zdoom() {
+        let args = engine_args(DoomEngineKind::ZDoom);
+        assert_eq!(
+            args.translate(Opt::Warp, &warp_values("E1M2")).unwrap(),
+            ["+map", "E1M2"]
+        );
+        assert_eq!(
+            args.translate(Opt::Skill, &skill_values(4, DoomEngineKind::ZDoom))
+                .unwrap(),
+            ["+skill", "3"]
+        );
+        assert_eq!(
+            args.translate(Opt::Geometry, &geometry_values("640x480"))
+                .unwrap(),
+            ["-width", "640", "-height", "480"]
+        );
+    }
+
+    #[test]
+    fn missing_value() {
+        let args = engine_args(DoomEngineKind::ZDoom);
+        match args.translate(Opt::Geometry, &geometry_values("640")) {
+            Err(Error::MissingValue { option, name, .. }) => {
+                assert_eq!(option, "--geometry");
+                assert_eq!(name, "width");
+            }
+            _ => panic!("expected MissingValue"),
+        }
+        let args = engine_args(DoomEngineKind::Boom);
+        assert!(matches!(
+            args.translate(Opt::Warp, &warp_values("START")),
+            Err(Error::MissingValue { .. })
+        ));
+    }
+}
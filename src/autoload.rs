@@ -6,6 +6,7 @@ use crate::doom_dir;
 use crate::error::Error;
 use crate::pwads::Pwads;
 use crate::search::search_files;
+use crate::settings::Settings;
 use crate::FileType;
 
 use std::collections::HashMap;
@@ -24,6 +25,7 @@ pub(crate) fn autoload(
     pwads: &mut Pwads,
     engine: impl AsRef<Path>,
     iwad: &str,
+    settings: &Settings,
 ) -> Result<(), Error> {
     let autoload_path = doom_dir()?.join("autoloads.ron");
     File::open(&autoload_path).or_else(|e| {
@@ -64,7 +66,7 @@ pub(crate) fn autoload(
         error: e,
     })?;
 
-    let universal_pwads = search_files(&autoloads.universal, FileType::Pwad)?;
+    let universal_pwads = search_files(&autoloads.universal, FileType::Pwad, settings)?;
     pwads.add_wads(universal_pwads);
 
     autoloads
@@ -78,12 +80,16 @@ pub(crate) fn autoload(
                 .as_ref(),
         )
         .map(|engine_specific_pwads| {
-            pwads.add_wads(search_files(engine_specific_pwads, FileType::Pwad)?);
+            pwads.add_wads(search_files(
+                engine_specific_pwads,
+                FileType::Pwad,
+                settings,
+            )?);
             Result::<(), Error>::Ok(())
         })
         .unwrap_or(Ok(()))?;
     if let Some(iwad_specific_pwads) = autoloads.iwad.get(iwad) {
-        pwads.add_wads(search_files(iwad_specific_pwads, FileType::Pwad)?);
+        pwads.add_wads(search_files(iwad_specific_pwads, FileType::Pwad, settings)?);
     }
     Ok(())
 }
@@ -0,0 +1,128 @@
+use crate::engine::DoomEngineKind;
+use crate::error::Error;
+use crate::iwad::Game;
+
+// `vanilla` is missing because it depends on the IWAD.
+const COMPLEVEL_NAMES: [(&str, i32); 14] = [
+    ("doom-1.2", 0),
+    ("doom-1.666", 1),
+    ("doom2-1.9", 2),
+    ("doom-1.9", 2),
+    ("ultimate", 3),
+    ("final", 4),
+    ("dosdoom", 5),
+    ("tasdoom", 6),
+    ("boom-2.01", 8),
+    ("boom", 9),
+    ("lxdoom", 10),
+    ("mbf", 11),
+    ("mbf21", 21),
+    // The extensions DSDA-Doom adds on top of MBF21 are not a separate complevel.
+    ("mbf21-ext", 21),
+];
+
+fn vanilla_complevel(game: Option<Game>) -> i32 {
+    match game {
+        Some(Game::Ultimate | Game::Freedoom1) => 3,
+        Some(Game::Tnt | Game::Plutonia) => 4,
+        _ => 2,
+    }
+}
+
+fn gameversion(complevel: i32) -> Option<&'static str> {
+    match complevel {
+        0 => Some("1.2"),
+        1 => Some("1.666"),
+        2 => Some("1.9"),
+        3 => Some("ultimate"),
+        4 => Some("final"),
+        _ => None,
+    }
+}
+
+pub(crate) fn demo_iwad_version(complevel: i32) -> Option<&'static str> {
+    match complevel {
+        0 => Some("1.2"),
+        1 => Some("1.666"),
+        2..=4 => Some("1.9"),
+        _ => None,
+    }
+}
+
+fn supported_by(complevel: i32, kind: DoomEngineKind) -> bool {
+    match kind {
+        DoomEngineKind::Vanilla => gameversion(complevel).is_some(),
+        // Negative complevels, such as -1 for the engine's default, are let through.
+        DoomEngineKind::Boom => complevel <= 17,
+        DoomEngineKind::MBF => complevel <= 17 || complevel == 21,
+        // These have no complevels of their own; their `args` decide.
+        DoomEngineKind::Eternity | DoomEngineKind::ZDoom => true,
+    }
+}
+
+pub(crate) fn resolve_complevel(
+    requested: &str,
+    kind: DoomEngineKind,
+    game: Option<Game>,
+) -> Result<i32, Error> {
+    let lowercase = requested.to_lowercase();
+    let complevel = match lowercase.parse::<i32>() {
+        Ok(complevel) => complevel,
+        Err(_) if lowercase == "vanilla" => vanilla_complevel(game),
+        Err(_) => COMPLEVEL_NAMES
+            .iter()
+            .find(|(name, _)| *name == lowercase)
+            .map(|(_, complevel)| *complevel)
+            .ok_or_else(|| Error::BadComplevel {
+                complevel: requested.to_owned(),
+                valid: std::iter::once("vanilla")
+                    .chain(COMPLEVEL_NAMES.iter().map(|(name, _)| *name))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })?,
+    };
+    if !supported_by(complevel, kind) {
+        return Err(Error::UnsupportedComplevel {
+            complevel: requested.to_owned(),
+            kind: kind.to_string(),
+        });
+    }
+    Ok(complevel)
+}
+
+pub(crate) fn complevel_values(complevel: i32) -> Vec<(&'static str, String)> {
+    let mut values = vec![("value", complevel.to_string())];
+    if let Some(gameversion) = gameversion(complevel) {
+        values.push(("gameversion", gameversion.to_owned()));
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_names() {
+        assert_eq!(
+            resolve_complevel("mbf21", DoomEngineKind::MBF, None).unwrap(),
+            21
+        );
+        assert_eq!(
+            resolve_complevel("MBF21-ext", DoomEngineKind::MBF, None).unwrap(),
+            21
+        );
+        assert!(matches!(
+            resolve_complevel("mbf21-ext", DoomEngineKind::Boom, None),
+            Err(Error::UnsupportedComplevel { .. })
+        ));
+        assert_eq!(
+            resolve_complevel("vanilla", DoomEngineKind::Boom, Some(Game::Plutonia)).unwrap(),
+            4
+        );
+        assert!(matches!(
+            resolve_complevel("mbf22", DoomEngineKind::MBF, None),
+            Err(Error::BadComplevel { .. })
+        ));
+    }
+}
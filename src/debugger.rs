@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cmd::CommandLine;
+use crate::cmd::Line;
+use crate::error::Error;
+use crate::launch::Launch;
+use crate::logs;
+use crate::settings::Settings;
+use crate::util::find_in_path;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub(crate) struct Debugger {
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub separator: Option<String>,
+    #[serde(default)]
+    pub batch_command: Option<Vec<String>>,
+    #[serde(default)]
+    pub crash_marker: Option<String>,
+}
+
+fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|w| w.to_string()).collect()
+}
+
+fn builtin_debuggers() -> Vec<(&'static str, Debugger)> {
+    vec![
+        (
+            "lldb",
+            Debugger {
+                command: words(&["lldb"]),
+                separator: Some("--".to_owned()),
+                batch_command: Some(words(&[
+                    "lldb", "--batch", "-o", "run", "-k", "bt all", "-k", "quit",
+                ])),
+                crash_marker: Some("stop reason = ".to_owned()),
+            },
+        ),
+        (
+            "gdb",
+            Debugger {
+                command: words(&["gdb", "--args"]),
+                separator: None,
+                batch_command: Some(words(&[
+                    "gdb",
+                    "-batch",
+                    "-ex",
+                    "run",
+                    "-ex",
+                    "thread apply all bt",
+                    "--args",
+                ])),
+                crash_marker: Some("received signal".to_owned()),
+            },
+        ),
+        (
+            "rr",
+            Debugger {
+                command: words(&["rr", "record"]),
+                separator: None,
+                batch_command: None,
+                crash_marker: None,
+            },
+        ),
+        (
+            "valgrind",
+            Debugger {
+                command: words(&["valgrind"]),
+                separator: None,
+                batch_command: None,
+                crash_marker: Some("Process terminating".to_owned()),
+            },
+        ),
+    ]
+}
+
+fn installed(program: &str) -> bool {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return program.exists();
+    }
+    find_in_path(program).is_some()
+}
+
+pub(crate) fn find_debugger(
+    requested: Option<&str>,
+    settings: &Settings,
+) -> Result<(String, Debugger), Error> {
+    let mut debuggers = builtin_debuggers()
+        .into_iter()
+        .map(|(name, debugger)| (name.to_owned(), debugger))
+        .collect::<BTreeMap<_, _>>();
+    debuggers.extend(settings.debuggers.clone());
+    let known = || debuggers.keys().cloned().collect::<Vec<_>>().join(", ");
+
+    let name = match requested.or(settings.default_debugger.as_deref()) {
+        Some(name) => name.to_owned(),
+        None => settings
+            .debuggers
+            .keys()
+            .map(|name| name.as_str())
+            .chain(builtin_debuggers().iter().map(|(name, _)| *name))
+            .find(|name| {
+                debuggers[*name]
+                    .command
+                    .first()
+                    .map(|program| installed(program))
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| Error::NoDebugger(known()))?
+            .to_owned(),
+    };
+    match debuggers.get(&name) {
+        Some(debugger) => Ok((name, debugger.clone())),
+        None => Err(Error::UnknownDebugger {
+            name,
+            known: known(),
+        }),
+    }
+}
+
+impl Debugger {
+    pub fn push_command(&self, cmdline: &mut CommandLine, batch: bool) {
+        let command = match &self.batch_command {
+            Some(batch_command) if batch => batch_command,
+            _ => &self.command,
+        };
+        for word in command {
+            cmdline.push_line(Line::from_word(word, 0));
+        }
+    }
+
+    pub fn push_separator(&self, cmdline: &mut CommandLine) {
+        if let Some(separator) = &self.separator {
+            cmdline.push_line(Line::from_word(separator, 0));
+        }
+    }
+
+    pub fn run_batch(&self, launch: &Launch, settings: &Settings) -> Result<(), Error> {
+        let session = launch.supervise(false, settings)?;
+        let log = match &session.log {
+            Some(log) => log,
+            None => return Ok(()),
+        };
+        let crashed = match &self.crash_marker {
+            Some(marker) => std::fs::read_to_string(log)
+                .map_err(Error::Io)?
+                .contains(marker.as_str()),
+            None => !session.status.success(),
+        };
+        if crashed {
+            let crash_log = logs::keep_as_crash_log(log)?;
+            eprintln!(
+                "The engine crashed; the debugger's output was saved to '{}'.",
+                crash_log.to_string_lossy()
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,559 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use walkdir::WalkDir;
+
+use crate::engine::DoomEngineKind;
+use crate::error::Error;
+use crate::iwad::Game;
+use crate::search::search_demo;
+use crate::settings::Settings;
+use crate::wad::lumps_in;
+
+const DEMO_END_MARKER: u8 = 0x80;
+const TICRATE: u32 = 35;
+const VANILLA_PLAYERS: usize = 4;
+const BOOM_PLAYERS: usize = 32;
+const BOOM_OPTION_SIZE: usize = 64;
+// Boom 2.00 wrote a larger options block than every later version.
+const BOOM_200_OPTION_SIZE: usize = 256;
+const MBF21_FIXED_OPTION_SIZE: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) enum DemoFormat {
+    Ancient,
+    Vanilla,
+    Boom,
+    LxDoom,
+    MBF,
+    PrBoom,
+    MBF21,
+}
+
+impl std::fmt::Display for DemoFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DemoFormat::Ancient => "Doom 1.2 or earlier",
+            DemoFormat::Vanilla => "vanilla",
+            DemoFormat::Boom => "Boom",
+            DemoFormat::LxDoom => "LxDoom",
+            DemoFormat::MBF => "MBF",
+            DemoFormat::PrBoom => "PrBoom/PrBoom+",
+            DemoFormat::MBF21 => "MBF21",
+        })
+    }
+}
+
+impl DemoFormat {
+    pub fn playable_by(&self, kind: DoomEngineKind) -> bool {
+        match kind {
+            DoomEngineKind::Vanilla => *self == DemoFormat::Vanilla,
+            DoomEngineKind::Boom => *self != DemoFormat::MBF21,
+            DoomEngineKind::MBF => !matches!(self, DemoFormat::LxDoom | DemoFormat::PrBoom),
+            DoomEngineKind::Eternity => matches!(
+                self,
+                DemoFormat::Vanilla | DemoFormat::Boom | DemoFormat::MBF
+            ),
+            DoomEngineKind::ZDoom => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DemoHeader {
+    pub version: Option<u8>,
+    pub format: DemoFormat,
+    // Vanilla 1.9 demos are reported as complevel 2; Ultimate Doom and Final Doom need the IWAD,
+    // see `complevel_for`.
+    pub complevel: Option<i32>,
+    pub skill: u8,
+    pub episode: u8,
+    pub map: u8,
+    pub deathmatch: u8,
+    pub respawn: bool,
+    pub fast: bool,
+    pub nomonsters: bool,
+    pub console_player: u8,
+    pub players: Vec<bool>,
+    pub longtics: bool,
+    header_size: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DemoFooter {
+    pub iwad: Option<String>,
+    pub files: Vec<String>,
+    pub dehs: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Demo {
+    pub path: PathBuf,
+    pub header: DemoHeader,
+    pub tics: u32,
+    pub footer: Option<DemoFooter>,
+}
+
+const FOOTER_CMDLINE_LUMP: &str = "CMDLINE";
+
+impl DemoFooter {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if !data.starts_with(b"PWAD") {
+            return None;
+        }
+        let lumps = lumps_in(data)?;
+        let (_, cmdline) = lumps.iter().find(|(name, _)| name == FOOTER_CMDLINE_LUMP)?;
+        let args = shlex::split(String::from_utf8_lossy(cmdline).trim_end_matches('\0'))?;
+
+        let mut footer = Self::default();
+        let mut current: Option<&mut Vec<String>> = None;
+        let mut iwad_next = false;
+        for arg in args {
+            if arg.starts_with('-') {
+                iwad_next = arg.eq_ignore_ascii_case("-iwad");
+                current = if arg.eq_ignore_ascii_case("-file") {
+                    Some(&mut footer.files)
+                } else if arg.eq_ignore_ascii_case("-deh") {
+                    Some(&mut footer.dehs)
+                } else {
+                    None
+                };
+            } else if iwad_next {
+                footer.iwad = Some(arg);
+                iwad_next = false;
+            } else if let Some(list) = current.as_mut() {
+                list.push(arg);
+            }
+        }
+        Some(footer)
+    }
+}
+
+fn bad_demo(path: &Path, reason: impl Into<String>) -> Error {
+    Error::BadDemo {
+        file: path.to_owned(),
+        reason: reason.into(),
+    }
+}
+
+impl DemoHeader {
+    fn parse(path: &Path, data: &[u8]) -> Result<Self, Error> {
+        let byte = |i: usize| {
+            data.get(i)
+                .copied()
+                .ok_or_else(|| bad_demo(path, "header is truncated"))
+        };
+        let version = byte(0)?;
+        match version {
+            // Doom 1.2 and earlier start directly with the skill, which is at most 4.
+            0..=4 => Ok(Self {
+                version: None,
+                format: DemoFormat::Ancient,
+                complevel: Some(0),
+                skill: version,
+                episode: byte(1)?,
+                map: byte(2)?,
+                deathmatch: 0,
+                respawn: false,
+                fast: false,
+                nomonsters: false,
+                console_player: 0,
+                players: (3..3 + VANILLA_PLAYERS)
+                    .map(|i| byte(i).map(|b| b != 0))
+                    .collect::<Result<_, _>>()?,
+                longtics: false,
+                header_size: 3 + VANILLA_PLAYERS,
+            }),
+            104..=111 => Ok(Self {
+                version: Some(version),
+                format: DemoFormat::Vanilla,
+                complevel: Some(if version <= 106 { 1 } else { 2 }),
+                skill: byte(1)?,
+                episode: byte(2)?,
+                map: byte(3)?,
+                deathmatch: byte(4)?,
+                respawn: byte(5)? != 0,
+                fast: byte(6)? != 0,
+                nomonsters: byte(7)? != 0,
+                console_player: byte(8)?,
+                players: (9..9 + VANILLA_PLAYERS)
+                    .map(|i| byte(i).map(|b| b != 0))
+                    .collect::<Result<_, _>>()?,
+                // Version 111 is what Chocolate Doom writes for -longtics.
+                longtics: version == 111,
+                header_size: 9 + VANILLA_PLAYERS,
+            }),
+            200..=203 | 210..=214 | 221 => {
+                let signature = data
+                    .get(1..7)
+                    .ok_or_else(|| bad_demo(path, "header is truncated"))?;
+                let lxdoom = version == 203 && &signature[1..4] == b"Boo";
+                let (format, complevel) = match version {
+                    200 | 201 => (DemoFormat::Boom, if byte(7)? != 0 { 7 } else { 8 }),
+                    202 => (DemoFormat::Boom, if byte(7)? != 0 { 7 } else { 9 }),
+                    203 if lxdoom => (DemoFormat::LxDoom, 10),
+                    203 => (DemoFormat::MBF, 11),
+                    210..=214 => (DemoFormat::PrBoom, version as i32 - 197),
+                    _ => (DemoFormat::MBF21, 21),
+                };
+                // LxDoom is the only one that wrote no compatibility byte after the signature.
+                let skill = if lxdoom { 7 } else { 8 };
+                let options = skill + 5;
+                let option_size = match version {
+                    200 => BOOM_200_OPTION_SIZE,
+                    221 => {
+                        MBF21_FIXED_OPTION_SIZE
+                            + 1
+                            + byte(options + MBF21_FIXED_OPTION_SIZE)? as usize
+                    }
+                    _ => BOOM_OPTION_SIZE,
+                };
+                // MBF21 dropped a few of the Boom options, which moves these flags.
+                let (respawn, fast, nomonsters) = if version == 221 {
+                    (options + 3, options + 4, options + 5)
+                } else {
+                    (options + 6, options + 7, options + 8)
+                };
+                let players = options + option_size;
+                Ok(Self {
+                    version: Some(version),
+                    format,
+                    complevel: Some(complevel),
+                    skill: byte(skill)?,
+                    episode: byte(skill + 1)?,
+                    map: byte(skill + 2)?,
+                    deathmatch: byte(skill + 3)?,
+                    console_player: byte(skill + 4)?,
+                    respawn: byte(respawn)? != 0,
+                    fast: byte(fast)? != 0,
+                    nomonsters: byte(nomonsters)? != 0,
+                    players: (players..players + BOOM_PLAYERS)
+                        .map(|i| byte(i).map(|b| b != 0))
+                        .collect::<Result<_, _>>()?,
+                    longtics: version == 214 || version == 221,
+                    header_size: players + BOOM_PLAYERS,
+                })
+            }
+            _ => Err(bad_demo(
+                path,
+                format!("unsupported demo version {}", version),
+            )),
+        }
+    }
+
+    pub fn complevel_for(&self, game: Option<Game>) -> Option<i32> {
+        match (self.format, game) {
+            (DemoFormat::Vanilla, Some(Game::Ultimate)) if self.complevel == Some(2) => Some(3),
+            (DemoFormat::Vanilla, Some(Game::Tnt | Game::Plutonia))
+                if self.complevel == Some(2) =>
+            {
+                Some(4)
+            }
+            _ => self.complevel,
+        }
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players.iter().filter(|p| **p).count()
+    }
+
+    fn tic_size(&self) -> usize {
+        if self.longtics {
+            5
+        } else {
+            4
+        }
+    }
+}
+
+impl Demo {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(Error::OpeningFile)?;
+        let header = DemoHeader::parse(path, &data)?;
+        let frame_size = header.tic_size() * header.player_count().max(1);
+
+        let mut tics = 0;
+        let mut i = header.header_size;
+        while i < data.len() && data[i] != DEMO_END_MARKER {
+            i += frame_size;
+            tics += 1;
+        }
+        let footer = data.get(i + 1..).and_then(DemoFooter::parse);
+
+        Ok(Self {
+            path: path.to_owned(),
+            header,
+            tics,
+            footer,
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.tics as u64 * 1000 / TICRATE as u64)
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{}:{:02}.{:02}",
+        secs / 60,
+        secs % 60,
+        duration.subsec_millis() / 10
+    )
+}
+
+fn collect_demos(demos: &[&str], settings: &Settings) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = vec![];
+    for demo in demos {
+        let path = Path::new(demo);
+        if path.is_dir() {
+            for entry in WalkDir::new(path).sort_by_file_name() {
+                let entry = entry?;
+                let is_lmp = entry
+                    .path()
+                    .extension()
+                    .map(|ext| ext.eq_ignore_ascii_case("lmp"))
+                    .unwrap_or(false);
+                if is_lmp && entry.file_type().is_file() {
+                    paths.push(entry.into_path());
+                }
+            }
+        } else if path.is_file() {
+            paths.push(path.to_owned());
+        } else {
+            paths.extend(search_demo(demo, settings)?);
+        }
+    }
+    Ok(paths)
+}
+
+pub(crate) fn info(demos: &[&str], settings: &Settings) -> Result<(), Error> {
+    for path in collect_demos(demos, settings)? {
+        let demo = match Demo::open(&path) {
+            Ok(d) => d,
+            Err(e) => {
+                println!("{}: {}", path.to_string_lossy(), e);
+                println!();
+                continue;
+            }
+        };
+        let header = &demo.header;
+        println!("{}:", demo.path.to_string_lossy());
+        println!(
+            "    format:     {}{}",
+            header.format,
+            header
+                .version
+                .map(|v| format!(" (version {})", v))
+                .unwrap_or_default()
+        );
+        if let Some(complevel) = header.complevel {
+            println!("    complevel:  {}", complevel);
+        }
+        println!("    skill:      {}", header.skill + 1);
+        println!(
+            "    map:        episode {}, map {}",
+            header.episode, header.map
+        );
+        println!(
+            "    players:    {} (recorded by player {})",
+            header.player_count(),
+            header.console_player + 1
+        );
+        let flags = [
+            (header.deathmatch != 0, "deathmatch"),
+            (header.respawn, "respawn"),
+            (header.fast, "fast"),
+            (header.nomonsters, "nomonsters"),
+            (header.longtics, "longtics"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+        if !flags.is_empty() {
+            println!("    flags:      {}", flags.join(", "));
+        }
+        println!(
+            "    length:     {} tics ({})",
+            demo.tics,
+            format_duration(demo.duration())
+        );
+        if let Some(footer) = &demo.footer {
+            if let Some(iwad) = &footer.iwad {
+                println!("    iwad:       {}", iwad);
+            }
+            if !footer.files.is_empty() {
+                println!("    files:      {}", footer.files.join(", "));
+            }
+            if !footer.dehs.is_empty() {
+                println!("    dehs:       {}", footer.dehs.join(", "));
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::tests::build_wad;
+    use crate::wad::tests::temp_file;
+
+    const BOOM_SIGNATURE: [u8; 6] = [0x1d, b'B', b'o', b'o', b'm', 0xe6];
+    const MBF_SIGNATURE: [u8; 6] = [0x1d, b'M', b'B', b'F', 0xe6, 0];
+
+    fn with_tics(mut header: Vec<u8>, tic_size: usize, footer: &[u8]) -> Vec<u8> {
+        header.extend(vec![1; 2 * tic_size]);
+        header.push(DEMO_END_MARKER);
+        header.extend(footer);
+        header
+    }
+
+    fn boom_header(version: u8, signature: [u8; 6], compatibility: Option<u8>) -> Vec<u8> {
+        let mut data = vec![version];
+        data.extend(signature);
+        data.extend(compatibility);
+        data.extend([3, 1, 7, 0, 0]);
+        let mut options = match version {
+            200 => vec![0; BOOM_200_OPTION_SIZE],
+            // Two comp flags follow the count; both are set.
+            221 => vec![0; MBF21_FIXED_OPTION_SIZE],
+            _ => vec![0; BOOM_OPTION_SIZE],
+        };
+        if version == 221 {
+            options[4] = 1;
+            options.extend([2, 1, 1]);
+        } else {
+            options[7] = 1;
+        }
+        data.extend(options);
+        data.push(1);
+        data.extend([0; BOOM_PLAYERS - 1]);
+        data
+    }
+
+    fn open(name: &str, data: &[u8]) -> Demo {
+        let path = temp_file(name, data);
+        let demo = Demo::open(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        demo
+    }
+
+    fn assert_boom_style(demo: &Demo, format: DemoFormat, complevel: i32) {
+        assert_eq!(demo.header.format, format);
+        assert_eq!(demo.header.complevel, Some(complevel));
+        assert_eq!((demo.header.skill, demo.header.map), (3, 7));
+        assert!(demo.header.fast && !demo.header.respawn && !demo.header.nomonsters);
+        assert_eq!(demo.header.player_count(), 1);
+        assert_eq!(demo.tics, 2);
+    }
+
+    #[test]
+    fn parses_vanilla_headers() {
+        let header = vec![109, 2, 1, 7, 0, 0, 1, 0, 0, 1, 1, 0, 0];
+        let demo = open("vanilla.lmp", &with_tics(header, 4, b""));
+        assert_eq!(demo.header.format, DemoFormat::Vanilla);
+        assert_eq!(demo.header.complevel, Some(2));
+        assert_eq!((demo.header.skill, demo.header.map), (2, 7));
+        assert!(demo.header.fast && !demo.header.longtics);
+        assert_eq!(demo.header.player_count(), 2);
+        // Two players make each tic twice as long.
+        assert_eq!(demo.tics, 1);
+
+        let ancient = open("ancient.lmp", &with_tics(vec![3, 1, 2, 1, 0, 0, 0], 4, b""));
+        assert_eq!(ancient.header.format, DemoFormat::Ancient);
+        assert_eq!((ancient.header.episode, ancient.header.map), (1, 2));
+        assert_eq!(ancient.tics, 2);
+    }
+
+    #[test]
+    fn parses_boom_headers() {
+        let demo = open(
+            "boom.lmp",
+            &with_tics(boom_header(202, BOOM_SIGNATURE, Some(0)), 4, b""),
+        );
+        assert_boom_style(&demo, DemoFormat::Boom, 9);
+        let demo = open(
+            "boom200.lmp",
+            &with_tics(boom_header(200, BOOM_SIGNATURE, Some(1)), 4, b""),
+        );
+        assert_boom_style(&demo, DemoFormat::Boom, 7);
+    }
+
+    #[test]
+    fn parses_lxdoom_and_mbf_headers() {
+        let demo = open(
+            "lxdoom.lmp",
+            &with_tics(boom_header(203, BOOM_SIGNATURE, None), 4, b""),
+        );
+        assert_boom_style(&demo, DemoFormat::LxDoom, 10);
+        let demo = open(
+            "mbf.lmp",
+            &with_tics(boom_header(203, MBF_SIGNATURE, Some(0)), 4, b""),
+        );
+        assert_boom_style(&demo, DemoFormat::MBF, 11);
+    }
+
+    #[test]
+    fn parses_prboom_headers() {
+        let demo = open(
+            "prboom.lmp",
+            &with_tics(boom_header(214, BOOM_SIGNATURE, Some(0)), 5, b""),
+        );
+        assert_boom_style(&demo, DemoFormat::PrBoom, 17);
+        assert!(demo.header.longtics);
+    }
+
+    #[test]
+    fn parses_mbf21_headers() {
+        let demo = open(
+            "mbf21.lmp",
+            &with_tics(boom_header(221, BOOM_SIGNATURE, Some(0)), 5, b""),
+        );
+        assert_boom_style(&demo, DemoFormat::MBF21, 21);
+        assert!(demo.header.longtics);
+    }
+
+    fn footer() -> Vec<u8> {
+        build_wad(
+            b"PWAD",
+            &[
+                ("VERSION", b"2"),
+                ("PORTNAME", b"PrBoom-Plus 2.6.66"),
+                (
+                    FOOTER_CMDLINE_LUMP,
+                    b"-iwad \"DOOM2.WAD\" -file \"sunlust.wad\" \"sunlust music.wad\" -deh \"sunlust.deh\" -complevel 11 -skill 4\0",
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn parses_footers() {
+        let demo = open(
+            "footer.lmp",
+            &with_tics(boom_header(214, BOOM_SIGNATURE, Some(0)), 5, &footer()),
+        );
+        let footer = demo.footer.unwrap();
+        assert_eq!(footer.iwad.as_deref(), Some("DOOM2.WAD"));
+        assert_eq!(footer.files, ["sunlust.wad", "sunlust music.wad"]);
+        assert_eq!(footer.dehs, ["sunlust.deh"]);
+    }
+
+    #[test]
+    fn ignores_wads_that_do_not_start_the_footer() {
+        let mut trailing = b"junk".to_vec();
+        trailing.extend(footer());
+        let demo = open(
+            "trailing.lmp",
+            &with_tics(boom_header(214, BOOM_SIGNATURE, Some(0)), 5, &trailing),
+        );
+        assert!(demo.footer.is_none());
+    }
+}
@@ -0,0 +1,356 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::engine::DoomEngineKind;
+use crate::iwad::Game;
+use crate::iwad::Iwad;
+use crate::maps::is_warpable;
+use crate::pwads::Pwads;
+use crate::wad::Wad;
+
+const MISSING_NAME_PREFIXES: [&str; 4] = [
+    "W_GetNumForName:",
+    "R_TextureNumForName:",
+    "R_FlatNumForName:",
+    "R_GetNumForName:",
+];
+
+const DEHACKED_SYMPTOMS: [&str; 5] = [
+    "P_SpawnMapThing: Unknown type",
+    "Unknown thing type",
+    "R_InstallSpriteLump",
+    "R_InitSprites",
+    "P_SetMobjState",
+];
+
+pub(crate) struct LaunchSummary<'a> {
+    pub kind: DoomEngineKind,
+    pub iwad: &'a Iwad,
+    pub pwads: &'a Pwads,
+    pub warp: Option<&'a str>,
+}
+
+pub(crate) struct Diagnosis {
+    pub problem: String,
+    pub suggestion: Option<String>,
+}
+
+struct PwadContents {
+    path: PathBuf,
+    maps: Vec<String>,
+    has_dehacked: bool,
+}
+
+fn read_pwads(pwads: &Pwads) -> Vec<PwadContents> {
+    pwads
+        .wads()
+        .iter()
+        .filter_map(|path| Wad::open(path).ok())
+        .map(|wad| PwadContents {
+            maps: wad.maps(),
+            has_dehacked: wad.has_lump("DEHACKED"),
+            path: wad.path,
+        })
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn missing_name(line: &str) -> Option<String> {
+    MISSING_NAME_PREFIXES.iter().find_map(|prefix| {
+        let rest = &line[line.find(prefix)? + prefix.len()..];
+        let name = rest
+            .split_whitespace()
+            .next()?
+            .trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-');
+        (!name.is_empty()).then(|| name.to_uppercase())
+    })
+}
+
+fn is_episodic(game: Game) -> bool {
+    matches!(
+        game,
+        Game::Shareware | Game::Registered | Game::Ultimate | Game::Freedoom1
+    )
+}
+
+fn iwad_mismatch(launch: &LaunchSummary, pwads: &[PwadContents]) -> Option<Diagnosis> {
+    let game = launch.iwad.game?;
+    let iwad = file_name(&launch.iwad.path);
+    if game == Game::Shareware && !pwads.is_empty() {
+        return Some(Diagnosis {
+            problem: format!(
+                "PWADs cannot be loaded with the shareware IWAD, but {} was loaded",
+                iwad
+            ),
+            suggestion: Some("load the registered or Ultimate Doom IWAD with -i doomu".to_owned()),
+        });
+    }
+    let (needed, flag) = if is_episodic(game) {
+        ("doom2.wad", "-i doom2")
+    } else {
+        ("doom.wad", "-i doomu")
+    };
+    let pwad = pwads.iter().find(|pwad| {
+        pwad.maps
+            .iter()
+            .any(|map| map.starts_with("MAP") == is_episodic(game) && is_warpable(map))
+    })?;
+    Some(Diagnosis {
+        problem: format!(
+            "'{}' requires {} but {} ({}) was loaded",
+            file_name(&pwad.path),
+            needed,
+            iwad,
+            game
+        ),
+        suggestion: Some(format!("pick the right IWAD with {}", flag)),
+    })
+}
+
+fn missing_map(map: &str, launch: &LaunchSummary, pwads: &[PwadContents]) -> Option<Diagnosis> {
+    let mut loaded = Wad::open(&launch.iwad.path)
+        .map(|wad| wad.maps())
+        .unwrap_or_default();
+    for pwad in pwads {
+        for name in &pwad.maps {
+            if !loaded.contains(name) {
+                loaded.push(name.clone());
+            }
+        }
+    }
+    if loaded.iter().any(|name| name.eq_ignore_ascii_case(map)) {
+        return None;
+    }
+    Some(Diagnosis {
+        problem: format!("map {} is not in the loaded WADs", map),
+        suggestion: Some(match loaded.len() {
+            0 => "check that the PWADs containing the map are loaded with -p".to_owned(),
+            1..=8 => format!(
+                "warp to one of {}, or pass -w without a map to pick one",
+                loaded.join(", ")
+            ),
+            n => format!("pass -w without a map to pick one of the {} loaded maps", n),
+        }),
+    })
+}
+
+fn missing_resource(name: &str, launch: &LaunchSummary, pwads: &[PwadContents]) -> Diagnosis {
+    if is_warpable(name) {
+        if let Some(diagnosis) = missing_map(name, launch, pwads) {
+            return diagnosis;
+        }
+    }
+    iwad_mismatch(launch, pwads).unwrap_or_else(|| Diagnosis {
+        problem: format!("{} is not in any of the loaded WADs", name),
+        suggestion: Some(
+            "if the PWAD needs a resource WAD or another PWAD, add it with -p".to_owned(),
+        ),
+    })
+}
+
+fn missing_dehacked(launch: &LaunchSummary, pwads: &[PwadContents]) -> Option<Diagnosis> {
+    if !launch.pwads.dehs().is_empty() || pwads.iter().any(|pwad| pwad.has_dehacked) {
+        return None;
+    }
+    let sibling = pwads.iter().find_map(|pwad| {
+        ["deh", "bex", "DEH", "BEX"]
+            .iter()
+            .map(|ext| pwad.path.with_extension(ext))
+            .find(|patch| patch.is_file())
+            .map(|patch| (pwad, patch))
+    });
+    Some(match sibling {
+        Some((pwad, patch)) => Diagnosis {
+            problem: format!(
+                "missing DEHACKED: {} comes with {}, which was not loaded",
+                file_name(&pwad.path),
+                file_name(&patch)
+            ),
+            suggestion: Some(format!("add it with -p {}", patch.to_string_lossy())),
+        },
+        None => Diagnosis {
+            problem: "missing DEHACKED: the PWADs define things no loaded patch describes"
+                .to_owned(),
+            suggestion: Some(
+                "add the .deh or .bex file that comes with the PWAD with -p".to_owned(),
+            ),
+        },
+    })
+}
+
+pub(crate) fn diagnose(output: &str, launch: &LaunchSummary) -> Vec<Diagnosis> {
+    let pwads = read_pwads(launch.pwads);
+    let mut diagnoses: Vec<Diagnosis> = vec![];
+    for line in output.lines() {
+        let diagnosis = if let Some(name) = missing_name(line) {
+            Some(missing_resource(&name, launch, &pwads))
+        } else if line.contains("P_SetupLevel") || line.contains("No such map") {
+            launch
+                .warp
+                .and_then(|map| missing_map(map, launch, &pwads))
+                .or_else(|| iwad_mismatch(launch, &pwads))
+        } else if line.contains("Z_Malloc") {
+            Some(Diagnosis {
+                problem: "the engine ran out of zone memory".to_owned(),
+                suggestion: Some(if launch.kind == DoomEngineKind::Vanilla {
+                    "give it more with `-- -mb 64` at the end of the command line".to_owned()
+                } else {
+                    "the PWAD may need a source port without a fixed memory limit; pick one with -e"
+                        .to_owned()
+                }),
+            })
+        } else if DEHACKED_SYMPTOMS.iter().any(|s| line.contains(s)) {
+            missing_dehacked(launch, &pwads)
+        } else {
+            None
+        };
+        if let Some(diagnosis) = diagnosis {
+            if !diagnoses.iter().any(|d| d.problem == diagnosis.problem) {
+                diagnoses.push(diagnosis);
+            }
+        }
+    }
+    diagnoses
+}
+
+pub(crate) fn report(log: &Path, launch: &LaunchSummary) {
+    let output = match std::fs::read(log) {
+        Ok(output) => output,
+        Err(_) => return,
+    };
+    for diagnosis in diagnose(&String::from_utf8_lossy(&output), launch) {
+        eprintln!("Problem: {}", diagnosis.problem);
+        if let Some(suggestion) = diagnosis.suggestion {
+            eprintln!("    Try: {}", suggestion);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::tests::build_wad;
+    use crate::wad::tests::temp_file;
+
+    fn wad(name: &str, magic: &[u8; 4], maps: &[&str], extra: &[&str]) -> PathBuf {
+        let lumps = maps
+            .iter()
+            .flat_map(|map| [(*map, &b""[..]), ("THINGS", &b"things"[..])])
+            .chain(extra.iter().map(|name| (*name, &b"lump"[..])))
+            .collect::<Vec<_>>();
+        temp_file(name, &build_wad(magic, &lumps))
+    }
+
+    fn doom2() -> Iwad {
+        Iwad {
+            path: wad("diagnose-doom2.wad", b"IWAD", &["MAP01", "MAP02"], &[]),
+            game: Some(Game::Doom2),
+        }
+    }
+
+    fn summary<'a>(iwad: &'a Iwad, pwads: &'a Pwads, warp: Option<&'a str>) -> LaunchSummary<'a> {
+        LaunchSummary {
+            kind: DoomEngineKind::Boom,
+            iwad,
+            pwads,
+            warp,
+        }
+    }
+
+    #[test]
+    fn pwad_for_another_iwad() {
+        let iwad = doom2();
+        let pwad = wad("diagnose-episodic.wad", b"PWAD", &["E1M1"], &[]);
+        let mut pwads = Pwads::new();
+        pwads.add_wad(&pwad);
+        let diagnoses = diagnose(
+            "P_Init: Init Playloop state.\nR_TextureNumForName: SW1BRN1 not available\n",
+            &summary(&iwad, &pwads, None),
+        );
+        assert_eq!(diagnoses.len(), 1);
+        assert_eq!(
+            diagnoses[0].problem,
+            format!(
+                "'{}' requires doom.wad but {} (Doom II) was loaded",
+                file_name(&pwad),
+                file_name(&iwad.path)
+            )
+        );
+        assert_eq!(
+            diagnoses[0].suggestion.as_deref(),
+            Some("pick the right IWAD with -i doomu")
+        );
+    }
+
+    #[test]
+    fn missing_map() {
+        let iwad = doom2();
+        let pwads = Pwads::new();
+        let diagnoses = diagnose(
+            "W_GetNumForName: MAP07 not found!\nW_GetNumForName: MAP07 not found!\n",
+            &summary(&iwad, &pwads, Some("MAP07")),
+        );
+        assert_eq!(diagnoses.len(), 1);
+        assert_eq!(diagnoses[0].problem, "map MAP07 is not in the loaded WADs");
+        assert_eq!(
+            diagnoses[0].suggestion.as_deref(),
+            Some("warp to one of MAP01, MAP02, or pass -w without a map to pick one")
+        );
+    }
+
+    #[test]
+    fn out_of_zone_memory() {
+        let iwad = doom2();
+        let pwads = Pwads::new();
+        let mut launch = summary(&iwad, &pwads, None);
+        launch.kind = DoomEngineKind::Vanilla;
+        let diagnoses = diagnose("Z_Malloc: failure on allocation of 65536 bytes", &launch);
+        assert_eq!(diagnoses.len(), 1);
+        assert_eq!(
+            diagnoses[0].suggestion.as_deref(),
+            Some("give it more with `-- -mb 64` at the end of the command line")
+        );
+    }
+
+    #[test]
+    fn missing_dehacked() {
+        let iwad = doom2();
+        let pwad = wad("diagnose-monsters.wad", b"PWAD", &["MAP01"], &[]);
+        let patch = temp_file("diagnose-monsters.deh", b"Patch File for DeHackEd v3.0");
+        let mut pwads = Pwads::new();
+        pwads.add_wad(&pwad);
+        let output = "P_SpawnMapThing: Unknown type 3010 at (0, 0)";
+        let diagnoses = diagnose(output, &summary(&iwad, &pwads, None));
+        assert_eq!(diagnoses.len(), 1);
+        assert_eq!(
+            diagnoses[0].suggestion,
+            Some(format!("add it with -p {}", patch.to_string_lossy()))
+        );
+
+        pwads.add_deh(patch);
+        assert!(diagnose(output, &summary(&iwad, &pwads, None)).is_empty());
+
+        let mut pwads = Pwads::new();
+        pwads.add_wad(wad(
+            "diagnose-own-deh.wad",
+            b"PWAD",
+            &["MAP01"],
+            &["DEHACKED"],
+        ));
+        assert!(diagnose(output, &summary(&iwad, &pwads, None)).is_empty());
+    }
+
+    #[test]
+    fn ignores_ordinary_output() {
+        let iwad = doom2();
+        let pwads = Pwads::new();
+        let output = "V_Init: allocate screens.\nM_LoadDefaults: Load system defaults.\n";
+        assert!(diagnose(output, &summary(&iwad, &pwads, None)).is_empty());
+    }
+}
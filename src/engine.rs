@@ -1,11 +1,17 @@
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::fmt::Display;
 use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
 use std::path::PathBuf;
 
 use indoc::indoc;
+use itertools::Itertools;
 use log::info;
 use log::trace;
 use log::warn;
+use ron::ser::PrettyConfig;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -19,7 +25,7 @@ const EXAMPLE_ENGINES_FILE: &str = indoc!(
             //     "example",
             //     "ex",
             // ],
-            // Path to the binary.
+            // Path to the binary, relative to this directory, or a name to look up in PATH.
             // binary: "/dev/zero",
             // What compatibility levels does this engine support?
             // Valid values: ("Vanilla", "Boom", "MBF", "Eternity", "ZDoom")
@@ -31,13 +37,38 @@ const EXAMPLE_ENGINES_FILE: &str = indoc!(
             // Optional, defaults to false.
             // If true, the launcher will use `-merge`, never `-file` nor `-deh`.
             // use_merge_arg: false,
+            // Is this the engine to use when -e is not given?
+            // Optional, defaults to false. Needed when more than one engine is defined.
+            // default: true,
+            // How does this engine take playdoom's options, if not like others of its kind?
+            // Optional. Words in braces are replaced by the option's values, and None marks an
+            // option the engine does not support.
+            // args: {
+            //     PistolStart: Some(["-pistolstart"]),
+            //     Geometry: Some(["-width", "{width}", "-height", "{height}"]),
+            //     Complevel: None,
+            // },
+            // Which skill to warp with when -s is not given, overriding settings.ron.
+            // Optional. A number from 1 to 5, or itytd, hntr, hmp, uv or nm.
+            // default_skill: Some("hmp"),
+            // Environment variables to set for the engine. Optional.
+            // env: {
+            //     "SDL_VIDEODRIVER": "wayland",
+            // },
+            // Where to run the engine from. Optional, defaults to the directory of the binary.
+            // Relative paths are relative to your Doom directory.
+            // working_dir: Some("/home/me/doom/saves"),
+            // Commands to run the engine through, outermost first. Optional.
+            // wrappers: ["gamemoderun", "prime-run"],
         // ),
     }
     "#
 );
 
+use crate::args::ArgTable;
 use crate::error::Error;
 use crate::util::absolute_path;
+use crate::util::find_in_path;
 
 #[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy)]
 #[allow(clippy::upper_case_acronyms)]
@@ -49,47 +80,139 @@ pub(crate) enum DoomEngineKind {
     ZDoom,
 }
 
+pub(crate) const ENGINE_KINDS: [&str; 5] = ["Vanilla", "Boom", "MBF", "Eternity", "ZDoom"];
+
+impl DoomEngineKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "vanilla" => Some(Self::Vanilla),
+            "boom" => Some(Self::Boom),
+            "mbf" => Some(Self::MBF),
+            "eternity" => Some(Self::Eternity),
+            "zdoom" => Some(Self::ZDoom),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DoomEngineKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Vanilla => "Vanilla",
+            Self::Boom => "Boom",
+            Self::MBF => "MBF",
+            Self::Eternity => "Eternity",
+            Self::ZDoom => "ZDoom",
+        })
+    }
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub(crate) struct DoomEngine {
     aliases: Vec<String>,
     pub binary: PathBuf,
     pub kind: DoomEngineKind,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub required_args: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub use_merge_arg: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub default: bool,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub args: ArgTable,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_skill: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wrappers: Vec<String>,
+}
+
+impl DoomEngine {
+    pub fn new(
+        aliases: Vec<String>,
+        binary: PathBuf,
+        kind: DoomEngineKind,
+        required_args: Vec<String>,
+        use_merge_arg: bool,
+        default: bool,
+    ) -> Self {
+        Self {
+            aliases,
+            binary,
+            kind,
+            required_args,
+            use_merge_arg,
+            default,
+            args: HashMap::new(),
+            default_skill: None,
+            env: BTreeMap::new(),
+            working_dir: None,
+            wrappers: vec![],
+        }
+    }
 }
 
 pub(crate) struct KnownEngines {
     alias_map: HashMap<String, usize>,
+    names: Vec<String>,
     engines: Vec<DoomEngine>,
 }
 
-pub(crate) struct KnownEnginesIterator {
-    iter: Box<dyn Iterator<Item = String>>,
-}
-
-impl Iterator for KnownEnginesIterator {
-    type Item = String;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next()
-    }
-}
-
 impl KnownEngines {
     pub fn new(engine_map: HashMap<String, DoomEngine>) -> Self {
         let mut alias_map = HashMap::new();
+        let mut names = Vec::new();
         let mut engines = Vec::new();
-        for (name, eng) in engine_map {
+        for (name, eng) in engine_map.into_iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
             let i = engines.len();
-            alias_map.insert(name, i);
+            if let Some(j) = alias_map.insert(name.clone(), i) {
+                warn!(
+                    "'{}' is both an engine and an alias of '{}'; the engine wins.",
+                    name, names[j]
+                );
+            }
             for alias in eng.aliases.iter() {
-                alias_map.insert(alias.clone(), i);
+                match alias_map.get(alias) {
+                    Some(&j) if j != i => warn!(
+                        "The alias '{}' of '{}' already refers to '{}', which wins.",
+                        alias, name, names[j]
+                    ),
+                    Some(_) => {}
+                    None => {
+                        alias_map.insert(alias.clone(), i);
+                    }
+                }
             }
+            names.push(name);
             engines.push(eng);
         }
-        Self { alias_map, engines }
+        Self {
+            alias_map,
+            names,
+            engines,
+        }
+    }
+
+    pub fn default_name(&self) -> Result<&str, Error> {
+        let defaults = self
+            .engines
+            .iter()
+            .positions(|e| e.default)
+            .collect::<Vec<_>>();
+        let index = match (defaults.as_slice(), self.engines.len()) {
+            (_, 0) => return Err(Error::NoEngines),
+            ([i], _) => *i,
+            ([], 1) => 0,
+            _ => return Err(Error::AmbiguousDefaultEngine(self.names.iter().join(", "))),
+        };
+        Ok(&self.names[index])
     }
 
     pub fn get(&self, name: &str) -> Option<&DoomEngine> {
@@ -97,21 +220,25 @@ impl KnownEngines {
         Some(&self.engines[index])
     }
 
-    pub fn iter(&self) -> KnownEnginesIterator {
-        let engines = self.engines.clone();
-        KnownEnginesIterator {
-            iter: Box::new(
-                engines
-                    .into_iter()
-                    .map(|e| e.aliases)
-                    .flat_map(|ss| ss.into_iter()),
-            ),
-        }
+    pub fn name_of(&self, name: &str) -> Option<&str> {
+        let index = *self.alias_map.get(name)?;
+        Some(&self.names[index])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &DoomEngine)> {
+        self.names
+            .iter()
+            .map(|n| n.as_str())
+            .zip(self.engines.iter())
     }
 }
 
-pub(crate) fn read_known_engines() -> Result<KnownEngines, Error> {
-    let engines_json_path = crate::doom_dir()?.join("engines.ron");
+fn engines_path() -> Result<PathBuf, Error> {
+    crate::doom_dir().map(|d| d.join("engines.ron"))
+}
+
+fn read_engines_file() -> Result<HashMap<String, DoomEngine>, Error> {
+    let engines_json_path = engines_path()?;
     trace!(
         "Searching for Doom engine definitions in {}",
         engines_json_path.to_string_lossy()
@@ -124,17 +251,29 @@ pub(crate) fn read_known_engines() -> Result<KnownEngines, Error> {
         write!(f, "{}", EXAMPLE_ENGINES_FILE).map_err(Error::Io)?;
     }
 
-    let engines: HashMap<String, DoomEngine> = ron::from_str(&String::from_utf8_lossy(
+    ron::from_str(&String::from_utf8_lossy(
         &std::fs::read(engines_json_path.as_path()).map_err(Error::Io)?,
     ))
     .map_err(|error| Error::BadRon {
         file: engines_json_path,
         error,
-    })?;
+    })
+}
+
+// A bare name that is not in the doom dir is looked up in `PATH`, or kept as it is.
+fn binary_path(binary: &Path) -> Result<PathBuf, Error> {
+    if binary.components().count() > 1 || crate::doom_dir()?.join(binary).exists() {
+        return absolute_path(binary);
+    }
+    Ok(find_in_path(binary).unwrap_or_else(|| binary.to_owned()))
+}
+
+pub(crate) fn read_known_engines() -> Result<KnownEngines, Error> {
+    let engines = read_engines_file()?;
     let engines: HashMap<String, DoomEngine> = engines
         .into_iter()
         .map(|(name, mut engine)| {
-            absolute_path(&engine.binary).map(|binary| {
+            binary_path(&engine.binary).map(|binary| {
                 // normalize
                 engine.binary = binary;
                 (name, engine)
@@ -142,6 +281,364 @@ pub(crate) fn read_known_engines() -> Result<KnownEngines, Error> {
         })
         .collect::<Result<_, _>>()?;
     info!("Found engines:");
-    engines.keys().for_each(|eng| info!("    {}", eng));
+    engines.keys().sorted().for_each(|eng| info!("    {}", eng));
     Ok(KnownEngines::new(engines))
 }
+
+struct EntrySpan {
+    name: String,
+    start: usize,
+    value_end: usize,
+    end: usize,
+}
+
+// Comments are skipped, so that engines.ron can be edited without losing them.
+fn scan_entries(text: &str) -> Option<(Vec<EntrySpan>, usize)> {
+    let bytes = text.as_bytes();
+    let mut entries = vec![];
+    let mut current: Option<EntrySpan> = None;
+    let mut depth = 0;
+    let mut expect_key = false;
+    let mut last_token_end = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i - 1] == b'*' && bytes[i] == b'/') {
+                    i += 1;
+                }
+            }
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                if depth == 1 && expect_key {
+                    let name = ron::from_str(&text[start..=i.min(bytes.len() - 1)]).ok()?;
+                    current = Some(EntrySpan {
+                        name,
+                        start,
+                        value_end: 0,
+                        end: 0,
+                    });
+                    expect_key = false;
+                }
+                last_token_end = i + 1;
+            }
+            b'{' | b'(' | b'[' => {
+                depth += 1;
+                if depth == 1 {
+                    expect_key = true;
+                }
+                last_token_end = i + 1;
+            }
+            b'}' | b')' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(mut entry) = current.take() {
+                        entry.value_end = last_token_end;
+                        entry.end = last_token_end;
+                        entries.push(entry);
+                    }
+                    return Some((entries, i));
+                }
+                last_token_end = i + 1;
+            }
+            b',' if depth == 1 => {
+                if let Some(mut entry) = current.take() {
+                    entry.value_end = last_token_end;
+                    entry.end = i + 1;
+                    entries.push(entry);
+                }
+                expect_key = true;
+                last_token_end = i + 1;
+            }
+            c if c.is_ascii_whitespace() => {}
+            _ => last_token_end = i + 1,
+        }
+        i += 1;
+    }
+    None
+}
+
+fn line_start(text: &str, pos: usize) -> usize {
+    let before = &text[..pos];
+    let start = before.rfind('\n').map(|n| n + 1).unwrap_or(0);
+    if before[start..].trim().is_empty() {
+        start
+    } else {
+        pos
+    }
+}
+
+fn write_engines_text(path: &Path, text: &str) -> Result<(), Error> {
+    // Refuse to write something that would not load back.
+    ron::from_str::<HashMap<String, DoomEngine>>(text).map_err(|error| Error::BadRon {
+        file: path.to_owned(),
+        error,
+    })?;
+    std::fs::write(path, text).map_err(Error::Io)
+}
+
+fn print_engine(name: &str, engine: &DoomEngine) {
+    println!("{}{}", name, if engine.default { " (default)" } else { "" });
+    println!("    aliases: {}", engine.aliases.join(", "));
+    println!("    kind: {}", engine.kind);
+    println!("    binary: {}", engine.binary.to_string_lossy());
+    if !engine.required_args.is_empty() {
+        println!("    required args: {}", engine.required_args.join(" "));
+    }
+    if engine.use_merge_arg {
+        println!("    loads files with -merge");
+    }
+    for (var, value) in &engine.env {
+        println!("    env: {}={}", var, value);
+    }
+    if let Some(working_dir) = &engine.working_dir {
+        println!("    working dir: {}", working_dir.to_string_lossy());
+    }
+    if !engine.wrappers.is_empty() {
+        println!("    wrappers: {}", engine.wrappers.join(" "));
+    }
+}
+
+pub(crate) fn list() -> Result<(), Error> {
+    let engines = read_known_engines()?;
+    if engines.names.is_empty() {
+        return Err(Error::NoEngines);
+    }
+    for (name, engine) in engines.iter() {
+        print_engine(name, engine);
+    }
+    Ok(())
+}
+
+pub(crate) fn show(name: &str) -> Result<(), Error> {
+    let engines = read_known_engines()?;
+    let canonical = engines
+        .name_of(name)
+        .ok_or_else(|| Error::NoSuchEngine(name.to_owned()))?;
+    print_engine(canonical, engines.get(canonical).unwrap());
+    Ok(())
+}
+
+pub(crate) fn add(name: &str, mut engine: DoomEngine) -> Result<(), Error> {
+    let engines = read_known_engines()?;
+    for taken in std::iter::once(name).chain(engine.aliases.iter().map(|a| a.as_str())) {
+        if let Some(owner) = engines.name_of(taken) {
+            return Err(Error::EngineExists {
+                name: taken.to_owned(),
+                owner: owner.to_owned(),
+            });
+        }
+    }
+    if !engine.aliases.iter().any(|a| a == name) {
+        engine.aliases.insert(0, name.to_owned());
+    }
+    if engine.default {
+        if let Some((other, _)) = engines.iter().find(|(_, e)| e.default) {
+            warn!(
+                "'{}' is already the default engine; remove `default: true` from one of them.",
+                other
+            );
+        }
+    }
+    if !binary_path(&engine.binary)?.exists() {
+        warn!(
+            "'{}' does not exist (yet); `playdoom engines check` will complain.",
+            engine.binary.to_string_lossy()
+        );
+    }
+
+    let path = engines_path()?;
+    let mut text = std::fs::read_to_string(&path).map_err(Error::Io)?;
+    let (entries, close) =
+        scan_entries(&text).ok_or_else(|| Error::BadEnginesFile(path.clone()))?;
+    let value = ron::ser::to_string_pretty(&engine, PrettyConfig::default().struct_names(true))?;
+    let entry = format!(
+        "    {}: {},\n",
+        ron::to_string(name)?,
+        value.replace('\n', "\n    ")
+    );
+    let mut insert_at = line_start(&text, close);
+    let entry = if !text[..insert_at].ends_with('\n') {
+        // The closing brace shares its line with the last entry.
+        insert_at = text[..close].trim_end().len();
+        format!("\n{}", entry)
+    } else {
+        entry
+    };
+    text.insert_str(insert_at, &entry);
+    if let Some(last) = entries.last() {
+        if last.end == last.value_end {
+            text.insert(last.value_end, ',');
+        }
+    }
+    write_engines_text(&path, &text)?;
+    println!("Added '{}' to {}.", name, path.to_string_lossy());
+    Ok(())
+}
+
+pub(crate) fn remove(name: &str) -> Result<(), Error> {
+    let engines = read_known_engines()?;
+    let canonical = engines
+        .name_of(name)
+        .ok_or_else(|| Error::NoSuchEngine(name.to_owned()))?
+        .to_owned();
+
+    let path = engines_path()?;
+    let mut text = std::fs::read_to_string(&path).map_err(Error::Io)?;
+    let (entries, _) = scan_entries(&text).ok_or_else(|| Error::BadEnginesFile(path.clone()))?;
+    let entry = entries
+        .iter()
+        .find(|e| e.name == canonical)
+        .ok_or_else(|| Error::BadEnginesFile(path.clone()))?;
+    let mut range: Range<usize> = line_start(&text, entry.start)..entry.end;
+    let rest_of_line = text[range.end..].find('\n').map(|n| range.end + n + 1);
+    if let Some(line_end) = rest_of_line {
+        if text[range.end..line_end].trim().is_empty() {
+            range.end = line_end;
+        }
+    }
+    text.replace_range(range, "");
+    write_engines_text(&path, &text)?;
+    println!("Removed '{}' from {}.", canonical, path.to_string_lossy());
+    Ok(())
+}
+
+fn is_executable(path: &Path) -> bool {
+    let metadata = match std::fs::metadata(path) {
+        Ok(m) if m.is_file() => m,
+        _ => return false,
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        true
+    }
+}
+
+pub(crate) fn check() -> Result<(), Error> {
+    let engines = read_engines_file()?;
+    let mut problems = 0;
+    let mut owners: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (name, engine) in engines.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+        let binary = binary_path(&engine.binary)?;
+        if !binary.exists() {
+            println!("{}: '{}' does not exist", name, binary.to_string_lossy());
+            problems += 1;
+        } else if !is_executable(&binary) {
+            println!("{}: '{}' is not executable", name, binary.to_string_lossy());
+            problems += 1;
+        }
+        let owned = owners.entry(name).or_default();
+        owned.push(name);
+        for alias in engine.aliases.iter().filter(|a| *a != name).unique() {
+            owners.entry(alias).or_default().push(name);
+        }
+    }
+    for (alias, names) in owners.iter().sorted() {
+        if names.len() > 1 {
+            println!(
+                "'{}' refers to more than one engine: {}",
+                alias,
+                names.join(", ")
+            );
+            problems += 1;
+        }
+    }
+    let defaults = engines.values().filter(|e| e.default).count();
+    if defaults > 1 {
+        println!("{} engines are marked as the default", defaults);
+        problems += 1;
+    }
+    if problems > 0 {
+        return Err(Error::EngineProblems(problems));
+    }
+    println!("All {} engines look fine.", engines.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(entries: &[EntrySpan]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn skips_comments() {
+        let text = indoc!(
+            r#"
+            {
+                // "commented": DoomEngine(binary: "x"),
+                "dsda": DoomEngine(binary: "dsda-doom"), // "trailing": (),
+                /* "block": DoomEngine(
+                    binary: "y",
+                ), */
+                "woof": DoomEngine(binary: "woof"),
+            }
+            "#
+        );
+        let (entries, close) = scan_entries(text).unwrap();
+        assert_eq!(names(&entries), ["dsda", "woof"]);
+        assert_eq!(close, text.rfind('}').unwrap());
+        let dsda = &entries[0];
+        assert_eq!(
+            &text[dsda.start..dsda.value_end],
+            r#""dsda": DoomEngine(binary: "dsda-doom")"#
+        );
+        assert_eq!(dsda.end, dsda.value_end + 1);
+    }
+
+    #[test]
+    fn nested_parentheses_and_brackets() {
+        let text = r#"{"a": DoomEngine(env: {"X": "1"}, aliases: ["b", "c"], working_dir: Some("d")), "e": DoomEngine(binary: "f")}"#;
+        let (entries, close) = scan_entries(text).unwrap();
+        assert_eq!(names(&entries), ["a", "e"]);
+        assert_eq!(
+            &text[entries[0].start..entries[0].value_end],
+            r#""a": DoomEngine(env: {"X": "1"}, aliases: ["b", "c"], working_dir: Some("d"))"#
+        );
+        // The last entry has no trailing comma.
+        assert_eq!(entries[1].end, entries[1].value_end);
+        assert_eq!(&text[entries[1].value_end..], "}");
+        assert_eq!(close, text.len() - 1);
+    }
+
+    #[test]
+    fn strings_with_closing_delimiters() {
+        let text =
+            r#"{"a)": DoomEngine(binary: "x)y", required_args: ["}", "\")"]), "b": DoomEngine()}"#;
+        let (entries, close) = scan_entries(text).unwrap();
+        assert_eq!(names(&entries), ["a)", "b"]);
+        assert_eq!(
+            &text[entries[0].start..entries[0].value_end],
+            r#""a)": DoomEngine(binary: "x)y", required_args: ["}", "\")"])"#
+        );
+        assert_eq!(close, text.len() - 1);
+    }
+
+    #[test]
+    fn unclosed() {
+        assert!(scan_entries(r#"{"a": DoomEngine(binary: "x")"#).is_none());
+        assert!(scan_entries(r#"{"a": DoomEngine(binary: "x)}"#).is_none());
+    }
+}
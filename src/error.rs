@@ -1,4 +1,3 @@
-use crate::job::Job;
 use std::io;
 use std::path::PathBuf;
 use std::sync::mpsc::RecvError;
@@ -6,13 +5,45 @@ use std::sync::mpsc::SendError;
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum Error {
+    #[error("cannot tell which engine to use; pass -e, or mark one engine in engines.ron with `default: true`. Engines: {0}")]
+    AmbiguousDefaultEngine(String),
+    #[error("unknown complevel '{complevel}'; use a number or one of: {valid}")]
+    BadComplevel { complevel: String, valid: String },
+    #[error("cannot find the entries in '{0}'; edit it by hand")]
+    BadEnginesFile(PathBuf),
     #[error("'{file}' contains bad RON: {error}")]
     BadRon {
         file: PathBuf,
         error: ron::error::SpannedError,
     },
+    #[error("'{file}' is not a demo playdoom can read: {reason}")]
+    BadDemo { file: PathBuf, reason: String },
+    #[error("'{file}' is not a valid WAD: {reason}")]
+    BadWad { file: PathBuf, reason: String },
+    #[error("unknown skill '{0}'; use a number from 1 to 5 or one of: itytd, hntr, hmp, uv, nm")]
+    BadSkill(String),
     #[error("creating autoloads file in your Doom directory: {0}")]
     CreatingAutoloadsFile(io::Error),
+    #[error("'{demo}' was not recorded with {flag}, so playing it back with {flag} would desync")]
+    DemoConflict { demo: PathBuf, flag: &'static str },
+    #[error("the command line for '{0}' is empty")]
+    EmptyCommandLine(String),
+    #[error("'{engine}' exited with status {code}{log}")]
+    EngineFailed {
+        engine: String,
+        code: i32,
+        log: String,
+    },
+    #[error("'{engine}' was killed by signal {signal}{log}")]
+    EngineKilled {
+        engine: String,
+        signal: i32,
+        log: String,
+    },
+    #[error("'{name}' already refers to the engine '{owner}'")]
+    EngineExists { name: String, owner: String },
+    #[error("found {0} problem(s) with your engines")]
+    EngineProblems(usize),
     #[error("file not found: '{0}'")]
     FileNotFound(String),
     #[error("formatter error: {0}")]
@@ -21,8 +52,20 @@ pub(crate) enum Error {
     Homeless,
     #[error("I/O error: {0}")]
     Io(io::Error),
+    #[error("none of these debuggers is installed: {0}; set `default_debugger` in settings.ron")]
+    NoDebugger(String),
     #[error("no engines defined")]
     NoEngines,
+    #[error("no engine is called '{0}'")]
+    NoSuchEngine(String),
+    #[error("map {map} is not in the loaded WADs; valid maps are: {valid}")]
+    NoSuchMap { map: String, valid: String },
+    #[error("no maps were found in the loaded WADs")]
+    NoMaps,
+    #[error("there is no launch #{n} in the history, which has {recorded}")]
+    NoSuchLaunch { n: usize, recorded: usize },
+    #[error("no profile is called '{name}'; profiles.ron has: {known}")]
+    NoSuchProfile { name: String, known: String },
     #[error("no file stem in '{0}'")]
     NoFileStem(String),
     #[error("attempting to open a file: {0}")]
@@ -32,9 +75,25 @@ pub(crate) enum Error {
     #[error("could not run Doom: {0}")]
     RunningDoom(io::Error),
     #[error("sending to interrupt handler: {0}")]
-    Send(Box<SendError<Result<Job, Error>>>),
+    Send(Box<SendError<Result<PathBuf, Error>>>),
+    #[error("serializing RON: {0}")]
+    SerializingRon(#[from] ron::Error),
     #[error("handling interrupt: {0}")]
     SignalHandler(ctrlc::Error),
+    #[error("unknown debugger '{name}'; add it to settings.ron or use one of: {known}")]
+    UnknownDebugger { name: String, known: String },
+    #[error("{kind} engines cannot emulate complevel '{complevel}'")]
+    UnsupportedComplevel { complevel: String, kind: String },
+    #[error(
+        "'{engine}' does not support {option}; if it does, say how under `args` in engines.ron"
+    )]
+    UnsupportedOption { engine: String, option: String },
+    #[error("'{engine}' takes {option} with a {{{name}}} that this value does not have")]
+    MissingValue {
+        engine: String,
+        option: String,
+        name: String,
+    },
     #[error("non-UTF-8 path: '{0}'")]
     NonUtf8Path(String),
     #[error("walking directory: {0}")]
@@ -0,0 +1,161 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use log::warn;
+use ron::ser::PrettyConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::diagnose::LaunchSummary;
+use crate::engine::DoomEngineKind;
+use crate::error::Error;
+use crate::iwad::Iwad;
+use crate::launch::Launch;
+use crate::launch::SavedLaunch;
+use crate::pwads::Pwads;
+use crate::settings::Settings;
+use crate::util::format_utc;
+use crate::util::now_secs;
+use crate::util::recorded_file_name;
+
+#[derive(Deserialize, Serialize)]
+struct HistoryEntry {
+    time: u64,
+    engine: String,
+    kind: DoomEngineKind,
+    iwad: PathBuf,
+    #[serde(default)]
+    pwads: Vec<PathBuf>,
+    #[serde(default)]
+    dehs: Vec<PathBuf>,
+    #[serde(default)]
+    warp: Option<String>,
+    #[serde(default)]
+    record: Option<PathBuf>,
+    launch: SavedLaunch,
+}
+
+fn history_path() -> Result<PathBuf, Error> {
+    Ok(crate::doom_dir()?.join("history.ron"))
+}
+
+fn read_history() -> Result<Vec<HistoryEntry>, Error> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    ron::from_str(&String::from_utf8_lossy(
+        &std::fs::read(&path).map_err(Error::Io)?,
+    ))
+    .map_err(|error| Error::BadRon { file: path, error })
+}
+
+fn append(entry: HistoryEntry) -> Result<(), Error> {
+    let mut history = read_history()?;
+    history.push(entry);
+    std::fs::write(
+        history_path()?,
+        ron::ser::to_string_pretty(&history, PrettyConfig::default())?,
+    )
+    .map_err(Error::Io)
+}
+
+// The game went fine, so a history that cannot be read or written only gets a warning.
+pub(crate) fn record(launch: &Launch, summary: &LaunchSummary, demo: Option<&Path>) {
+    let entry = HistoryEntry {
+        time: now_secs(),
+        engine: launch.name().to_owned(),
+        kind: summary.kind,
+        iwad: summary.iwad.path.clone(),
+        pwads: summary.pwads.wads().to_vec(),
+        dehs: summary.pwads.dehs().to_vec(),
+        warp: summary.warp.map(|map| map.to_owned()),
+        record: demo.map(|demo| demo.to_owned()),
+        launch: launch.save(),
+    };
+    if let Err(e) = append(entry) {
+        warn!("The launch was not added to the history: {}", e);
+    }
+}
+
+fn file_names(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| recorded_file_name(&p.to_string_lossy()).to_owned())
+        .join(", ")
+}
+
+pub(crate) fn list(count: usize) -> Result<(), Error> {
+    let history = read_history()?;
+    if history.is_empty() {
+        println!("No launches recorded yet.");
+        return Ok(());
+    }
+    for (n, entry) in history.iter().rev().enumerate().take(count) {
+        let wads = file_names(&entry.pwads);
+        println!(
+            "{:>3}  {}  {}  {}{}",
+            n + 1,
+            format_utc(entry.time),
+            entry.engine,
+            recorded_file_name(&entry.iwad.to_string_lossy()),
+            if wads.is_empty() {
+                String::new()
+            } else {
+                format!("  {}", wads)
+            }
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn again(n: usize, settings: &Settings) -> Result<(), Error> {
+    let mut history = read_history()?;
+    let recorded = history.len();
+    if n == 0 || n > recorded {
+        return Err(Error::NoSuchLaunch { n, recorded });
+    }
+    let entry = history.remove(recorded - n);
+    if let Some(demo) = &entry.record {
+        let overwrite = dialoguer::Confirm::new()
+            .with_prompt(format!(
+                "This launch recorded '{}'; running it again records over it. Continue?",
+                demo.to_string_lossy()
+            ))
+            .default(false)
+            .interact()
+            .map_err(Error::Io)?;
+        if !overwrite {
+            return Ok(());
+        }
+    }
+    let launch = Launch::restore(&entry.engine, entry.launch);
+    let iwad = Iwad::open(&entry.iwad)?;
+    let mut pwads = Pwads::new();
+    pwads.add_wads(entry.pwads);
+    for deh in entry.dehs {
+        pwads.add_deh(deh);
+    }
+    eprintln!("Playing again, as on {}:", format_utc(entry.time));
+    for line in launch.describe_environment() {
+        eprintln!("{}", line);
+    }
+    eprintln!(
+        "Command line: \n'\n{}\n'",
+        launch
+            .cmdline
+            .iter_lines()
+            .map(|l| l.iter().join(" "))
+            .join("\n")
+    );
+    let summary = LaunchSummary {
+        kind: entry.kind,
+        iwad: &iwad,
+        pwads: &pwads,
+        warp: entry.warp.as_deref(),
+    };
+    launch.play(&summary, settings)?;
+    record(&launch, &summary, entry.record.as_deref());
+    Ok(())
+}
@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::Metadata;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::UNIX_EPOCH;
+
+use log::info;
+use log::trace;
+use log::warn;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::iwad::IwadIdentity;
+use crate::settings::SearchRoot;
+use crate::settings::Settings;
+use crate::util::absolute_path;
+use crate::FileType;
+
+static INDEX: Lazy<Mutex<Option<LoadedIndex>>> = Lazy::new(|| Mutex::new(None));
+
+struct LoadedIndex {
+    index: FileIndex,
+    refreshed: HashSet<(PathBuf, Option<usize>)>,
+}
+
+// A directory is listed again only when its mtime changes, which happens whenever an entry is
+// added, removed or renamed in it. Overwriting a file leaves it alone, so what is known about a
+// file is also checked against the file's own mtime and size.
+#[derive(Default, Deserialize, Serialize)]
+struct FileIndex {
+    dirs: HashMap<OsString, IndexedDir>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct IndexedDir {
+    mtime: Duration,
+    entries: Vec<IndexedEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct IndexedEntry {
+    name: OsString,
+    is_dir: bool,
+    #[serde(default)]
+    is_symlink: bool,
+    mtime: Duration,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    iwad: Option<IwadIdentity>,
+}
+
+fn index_path() -> Result<PathBuf, Error> {
+    crate::doom_dir().map(|d| d.join("index.ron"))
+}
+
+fn mtime(metadata: &Metadata) -> Duration {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default()
+}
+
+impl FileIndex {
+    fn load() -> Result<Self, Error> {
+        let path = index_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        // The index is only a cache, so one that cannot be read is started again.
+        match ron::from_str(&String::from_utf8_lossy(
+            &std::fs::read(&path).map_err(Error::Io)?,
+        )) {
+            Ok(index) => Ok(index),
+            Err(e) => {
+                warn!(
+                    "Cannot read the index '{}', indexing again: {}",
+                    path.to_string_lossy(),
+                    e
+                );
+                Ok(Self::default())
+            }
+        }
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        // Names are stored as bytes, which pretty-printing would put one per line.
+        std::fs::write(index_path()?, ron::to_string(self)?).map_err(Error::Io)
+    }
+
+    fn list(dir: &Path) -> Vec<IndexedEntry> {
+        let read_dir = match std::fs::read_dir(dir) {
+            Ok(r) => r,
+            Err(e) => {
+                info!("Cannot list '{}': {}", dir.to_string_lossy(), e);
+                return vec![];
+            }
+        };
+        let mut entries = read_dir
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                // Follows symlinks, as the search always has.
+                let metadata = std::fs::metadata(entry.path()).ok()?;
+                Some(IndexedEntry {
+                    is_symlink: entry.file_type().ok()?.is_symlink(),
+                    name: entry.file_name(),
+                    is_dir: metadata.is_dir(),
+                    mtime: mtime(&metadata),
+                    size: metadata.len(),
+                    iwad: None,
+                })
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        entries
+    }
+
+    fn refresh(&mut self, root: &Path, depth: Option<usize>) -> bool {
+        let real = root.canonicalize().unwrap_or_else(|_| root.to_owned());
+        self.refresh_dir(root, real, None, depth, &mut HashSet::new())
+    }
+
+    fn refresh_dir(
+        &mut self,
+        dir: &Path,
+        real: PathBuf,
+        known_mtime: Option<Duration>,
+        depth: Option<usize>,
+        visited: &mut HashSet<PathBuf>,
+    ) -> bool {
+        if depth == Some(0) {
+            return false;
+        }
+        // Symlinks can form cycles, so each real directory is only visited once.
+        if !visited.insert(real.clone()) {
+            return false;
+        }
+        let dir_mtime = match known_mtime {
+            Some(mtime) => mtime,
+            None => match std::fs::metadata(dir) {
+                Ok(m) if m.is_dir() => mtime(&m),
+                _ => return self.dirs.remove(dir.as_os_str()).is_some(),
+            },
+        };
+
+        let mut listed = false;
+        if self.dirs.get(dir.as_os_str()).map(|d| d.mtime) != Some(dir_mtime) {
+            trace!("Indexing '{}'", dir.to_string_lossy());
+            let mut entries = Self::list(dir);
+            if let Some(old) = self.dirs.remove(dir.as_os_str()) {
+                // Files that were not touched keep what is known about them.
+                for entry in entries.iter_mut() {
+                    entry.iwad = old
+                        .entries
+                        .iter()
+                        .find(|o| {
+                            o.name == entry.name && o.mtime == entry.mtime && o.size == entry.size
+                        })
+                        .and_then(|o| o.iwad);
+                }
+                for gone in old
+                    .entries
+                    .iter()
+                    .filter(|o| o.is_dir && !entries.iter().any(|e| e.is_dir && e.name == o.name))
+                {
+                    let gone = dir.join(&gone.name);
+                    self.dirs.retain(|d, _| !Path::new(d).starts_with(&gone));
+                }
+            }
+            self.dirs.insert(
+                dir.as_os_str().to_owned(),
+                IndexedDir {
+                    mtime: dir_mtime,
+                    entries,
+                },
+            );
+            listed = true;
+        }
+
+        let subdirs = self.dirs[dir.as_os_str()]
+            .entries
+            .iter()
+            .filter(|e| e.is_dir)
+            .map(|e| {
+                let subdir = dir.join(&e.name);
+                let real = if e.is_symlink {
+                    subdir.canonicalize().unwrap_or_else(|_| subdir.clone())
+                } else {
+                    real.join(&e.name)
+                };
+                // The mtimes of a listing that was just made are current.
+                (subdir, real, listed.then_some(e.mtime))
+            })
+            .collect::<Vec<_>>();
+        let mut changed = listed;
+        for (subdir, real, known_mtime) in subdirs {
+            changed |= self.refresh_dir(&subdir, real, known_mtime, depth.map(|d| d - 1), visited);
+        }
+        changed
+    }
+
+    fn walk(&self, root: &Path, depth: Option<usize>, out: &mut Vec<(PathBuf, bool)>) {
+        if depth == Some(0) {
+            return;
+        }
+        if let Some(dir) = self.dirs.get(root.as_os_str()) {
+            for entry in &dir.entries {
+                let path = root.join(&entry.name);
+                out.push((path.clone(), entry.is_dir));
+                if entry.is_dir {
+                    self.walk(&path, depth.map(|d| d - 1), out);
+                }
+            }
+        }
+    }
+
+    fn identify(
+        &mut self,
+        path: &Path,
+        identify: impl Fn(&Path) -> IwadIdentity,
+    ) -> (IwadIdentity, bool) {
+        let entry = path
+            .parent()
+            .and_then(|dir| self.dirs.get_mut(dir.as_os_str()))
+            .and_then(|dir| {
+                dir.entries
+                    .iter_mut()
+                    .find(|e| Some(e.name.as_os_str()) == path.file_name())
+            });
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return (identify(path), false),
+        };
+        let (size, mtime) = std::fs::metadata(path)
+            .map(|metadata| (metadata.len(), mtime(&metadata)))
+            .unwrap_or_default();
+        match entry.iwad {
+            Some(identity) if entry.size == size && entry.mtime == mtime => (identity, false),
+            _ => {
+                entry.size = size;
+                entry.mtime = mtime;
+                (*entry.iwad.insert(identify(path)), true)
+            }
+        }
+    }
+
+    fn entries(&self, root: &Path, depth: Option<usize>) -> Vec<(PathBuf, bool)> {
+        let mut entries = vec![];
+        if root.is_dir() {
+            entries.push((root.to_owned(), true));
+            self.walk(root, depth, &mut entries);
+        }
+        entries
+    }
+}
+
+pub(crate) fn entries_under(root: &SearchRoot) -> Result<Vec<(PathBuf, bool)>, Error> {
+    let depth = root.max_depth;
+    let root = absolute_path(&root.path)?;
+    let mut guard = INDEX.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(LoadedIndex {
+            index: FileIndex::load()?,
+            refreshed: HashSet::new(),
+        });
+    }
+    let loaded = guard.as_mut().unwrap();
+
+    if loaded.refreshed.insert((root.clone(), depth)) && loaded.index.refresh(&root, depth) {
+        loaded.index.save()?;
+    }
+    Ok(loaded.index.entries(&root, depth))
+}
+
+pub(crate) fn entries_unindexed(root: &SearchRoot) -> Result<Vec<(PathBuf, bool)>, Error> {
+    let root_path = absolute_path(&root.path)?;
+    let mut index = FileIndex::default();
+    index.refresh(&root_path, root.max_depth);
+    Ok(index.entries(&root_path, root.max_depth))
+}
+
+pub(crate) fn iwads_under(
+    root: &SearchRoot,
+    wanted: impl Fn(&Path) -> bool,
+    identify: impl Fn(&Path) -> IwadIdentity,
+) -> Result<Vec<(PathBuf, IwadIdentity)>, Error> {
+    let files = entries_under(root)?
+        .into_iter()
+        .filter(|(path, is_dir)| !is_dir && wanted(path))
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+    let mut guard = INDEX.lock().unwrap();
+    let index = &mut guard.as_mut().unwrap().index;
+    let mut changed = false;
+    let mut identified = vec![];
+    for path in files {
+        let (identity, identified_now) = index.identify(&path, &identify);
+        changed |= identified_now;
+        identified.push((path, identity));
+    }
+    if changed {
+        index.save()?;
+    }
+    Ok(identified)
+}
+
+pub(crate) fn rebuild(settings: &Settings) -> Result<(), Error> {
+    let mut index = FileIndex::default();
+    let mut roots = vec![];
+    for ty in [FileType::Iwad, FileType::Pwad, FileType::Demo] {
+        for root in settings.search_paths(&ty)?.roots {
+            let root = (absolute_path(root.path)?, root.max_depth);
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
+        }
+    }
+    for (root, depth) in roots {
+        println!("Indexing '{}'", root.to_string_lossy());
+        index.refresh(&root, depth);
+    }
+    index.save()?;
+    println!(
+        "Indexed {} files in {} directories.",
+        index.dirs.values().map(|d| d.entries.len()).sum::<usize>(),
+        index.dirs.len()
+    );
+    *INDEX.lock().unwrap() = None;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use walkdir::WalkDir;
+
+    use super::*;
+    use crate::score::score_entry;
+
+    fn temp_tree(name: &str, files: &[&str]) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("playdoom-test-{}-{}", std::process::id(), name));
+        for file in files {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"").unwrap();
+        }
+        root
+    }
+
+    fn fresh_walk(root: &Path, depth: Option<usize>) -> Vec<(PathBuf, bool)> {
+        let mut walk = WalkDir::new(root).follow_links(true).sort_by_file_name();
+        if let Some(depth) = depth {
+            walk = walk.max_depth(depth);
+        }
+        walk.into_iter()
+            .map(|e| e.unwrap())
+            .map(|e| (e.path().to_owned(), e.file_type().is_dir()))
+            .collect()
+    }
+
+    fn ranking(entries: &[(PathBuf, bool)], term: &str) -> Vec<(usize, PathBuf)> {
+        let term = Path::new(term);
+        let ancestors = term
+            .ancestors()
+            .skip(1)
+            .map(|p| p.to_path_buf())
+            .collect::<Vec<_>>();
+        entries
+            .iter()
+            .map(|(path, is_dir)| {
+                let extension = path
+                    .extension()
+                    .map(|e| e.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let score = score_entry(
+                    path,
+                    *is_dir,
+                    term.file_stem().unwrap(),
+                    term.extension(),
+                    &extension,
+                    &ancestors,
+                )
+                .unwrap();
+                (score, path.clone())
+            })
+            .collect()
+    }
+
+    fn assert_same_ranking(index: &FileIndex, root: &Path, depth: Option<usize>) {
+        let indexed = index.entries(root, depth);
+        let walked = fresh_walk(root, depth);
+        for term in ["doom2", "doom2.wad", "DOOM2", "iwad/doom2", "sunlust.deh"] {
+            assert_eq!(ranking(&indexed, term), ranking(&walked, term), "{}", term);
+        }
+    }
+
+    #[test]
+    fn ranks_like_a_fresh_walk() {
+        let root = temp_tree(
+            "rank",
+            &[
+                "doom2.wad",
+                "DOOM2.WAD",
+                "doom2-1.9.wad",
+                "iwad/doom2.wad",
+                "pwads/sunlust/sunlust.wad",
+                "pwads/sunlust/sunlust.deh",
+                "doom2/readme.txt",
+            ],
+        );
+        let mut index = FileIndex::default();
+        index.refresh(&root, None);
+        assert_same_ranking(&index, &root, None);
+        assert_same_ranking(&index, &root, Some(2));
+
+        std::fs::write(root.join("pwads/sunlust/sunlust2.deh"), b"").unwrap();
+        std::fs::remove_file(root.join("iwad/doom2.wad")).unwrap();
+        assert!(index.refresh(&root, None));
+        assert_same_ranking(&index, &root, None);
+        assert!(!index.refresh(&root, None));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn identifies_overwritten_files_again() {
+        let root = temp_tree("identify", &["doom2.wad"]);
+        let path = root.join("doom2.wad");
+        let mut index = FileIndex::default();
+        index.refresh(&root, None);
+        let identified = std::cell::Cell::new(0);
+        let identify = |_: &Path| {
+            identified.set(identified.get() + 1);
+            IwadIdentity::NotIwad
+        };
+        assert_eq!(
+            index.identify(&path, identify),
+            (IwadIdentity::NotIwad, true)
+        );
+        assert_eq!(
+            index.identify(&path, identify),
+            (IwadIdentity::NotIwad, false)
+        );
+        assert_eq!(identified.get(), 1);
+
+        // Overwriting a file in place does not change the mtime of its directory, so the
+        // listing is not refreshed.
+        std::fs::write(&path, b"IWAD").unwrap();
+        assert_eq!(
+            index.identify(&path, identify),
+            (IwadIdentity::NotIwad, true)
+        );
+        assert_eq!(identified.get(), 2);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_names_that_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = temp_tree("utf8", &[]);
+        std::fs::create_dir_all(&root).unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.wad");
+        std::fs::write(root.join(name), b"").unwrap();
+        let mut index = FileIndex::default();
+        index.refresh(&root, None);
+        let stored = ron::to_string(&index).unwrap();
+        let index: FileIndex = ron::from_str(&stored).unwrap();
+        assert_eq!(index.entries(&root, None)[1].0, root.join(name));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
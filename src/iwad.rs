@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use log::info;
+use log::trace;
+use log::warn;
+use ron::ser::PrettyConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::index;
+use crate::pins;
+use crate::search::search_file;
+use crate::settings::Settings;
+use crate::util::absolute_path;
+use crate::util::modified_secs;
+use crate::wad::Wad;
+use crate::wad::WadKind;
+use crate::FileType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub(crate) enum Game {
+    Shareware,
+    Registered,
+    Ultimate,
+    Doom2,
+    Tnt,
+    Plutonia,
+    Freedoom1,
+    Freedoom2,
+}
+
+const GAME_PREFERENCE: [Game; 8] = [
+    Game::Doom2,
+    Game::Ultimate,
+    Game::Registered,
+    Game::Shareware,
+    Game::Plutonia,
+    Game::Tnt,
+    Game::Freedoom2,
+    Game::Freedoom1,
+];
+
+// Heretic and Hexen call their title screen TITLE, Heretic also has ADVISOR, Hexen a MAPINFO,
+// Strife ENDSTRF and Chex Quest the W94_1 patch. Their maps are named like Doom's.
+const OTHER_GAME_LUMPS: [&str; 5] = ["TITLE", "ADVISOR", "MAPINFO", "ENDSTRF", "W94_1"];
+
+impl Game {
+    pub fn identify(wad: &Wad) -> Option<Self> {
+        if wad.kind != WadKind::Iwad {
+            return None;
+        }
+        if wad.has_lump("FREEDOOM") {
+            return Some(if wad.has_lump("E1M1") {
+                Game::Freedoom1
+            } else {
+                Game::Freedoom2
+            });
+        }
+        if OTHER_GAME_LUMPS.iter().any(|lump| wad.has_lump(lump)) || !wad.has_lump("TITLEPIC") {
+            return None;
+        }
+        if wad.has_lump("MAP01") {
+            // CAVERN5 and MC11 are patches that only ship with TNT and Plutonia respectively.
+            Some(if wad.has_lump("CAVERN5") {
+                Game::Tnt
+            } else if wad.has_lump("MC11") {
+                Game::Plutonia
+            } else {
+                Game::Doom2
+            })
+        } else if wad.has_lump("E4M1") {
+            Some(Game::Ultimate)
+        } else if wad.has_lump("E2M1") {
+            Some(Game::Registered)
+        } else if wad.has_lump("E1M1") {
+            Some(Game::Shareware)
+        } else {
+            None
+        }
+    }
+
+    pub fn provides(self, requested: Game) -> bool {
+        self == requested || (self == Game::Ultimate && requested == Game::Registered)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "doom1" | "shareware" => Some(Game::Shareware),
+            "doom" | "registered" => Some(Game::Registered),
+            "doomu" | "ultimate" => Some(Game::Ultimate),
+            "doom2" => Some(Game::Doom2),
+            "tnt" => Some(Game::Tnt),
+            "plutonia" => Some(Game::Plutonia),
+            "freedoom1" => Some(Game::Freedoom1),
+            "freedoom2" | "freedoom" => Some(Game::Freedoom2),
+            _ => None,
+        }
+    }
+
+    pub fn canonical_stem(&self) -> &'static str {
+        match self {
+            Game::Shareware => "doom1",
+            Game::Registered => "doom",
+            Game::Ultimate => "doomu",
+            Game::Doom2 => "doom2",
+            Game::Tnt => "tnt",
+            Game::Plutonia => "plutonia",
+            Game::Freedoom1 => "freedoom1",
+            Game::Freedoom2 => "freedoom2",
+        }
+    }
+}
+
+impl std::fmt::Display for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Game::Shareware => "Doom (shareware)",
+            Game::Registered => "Doom (registered)",
+            Game::Ultimate => "The Ultimate Doom",
+            Game::Doom2 => "Doom II",
+            Game::Tnt => "TNT: Evilution",
+            Game::Plutonia => "The Plutonia Experiment",
+            Game::Freedoom1 => "Freedoom: Phase 1",
+            Game::Freedoom2 => "Freedoom: Phase 2",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) enum IwadIdentity {
+    NotIwad,
+    Iwad(Option<Game>),
+}
+
+fn identify(path: &Path) -> IwadIdentity {
+    if Wad::peek_kind(path).unwrap_or(None) != Some(WadKind::Iwad) {
+        return IwadIdentity::NotIwad;
+    }
+    IwadIdentity::Iwad(
+        Wad::open(path)
+            .map(|wad| Game::identify(&wad))
+            .unwrap_or(None),
+    )
+}
+
+pub(crate) struct Iwad {
+    pub path: PathBuf,
+    pub game: Option<Game>,
+}
+
+impl Iwad {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = absolute_path(path)?;
+        let game = Wad::open(&path)
+            .map(|wad| Game::identify(&wad))
+            .unwrap_or(None);
+        Ok(Self { path, game })
+    }
+
+    pub fn release(&self) -> Option<&'static KnownIwad> {
+        match cached_md5(&self.path) {
+            Ok(md5) => KNOWN_IWADS.iter().find(|k| k.md5 == md5),
+            Err(e) => {
+                warn!(
+                    "Cannot tell which release '{}' is: {}",
+                    self.path.to_string_lossy(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub fn describe(&self) -> Option<String> {
+        self.release()
+            .map(|known| format!("{} ({})", known.game, known.version))
+    }
+}
+
+pub(crate) fn find_iwads(settings: &Settings) -> Result<Vec<Iwad>, Error> {
+    let mut iwads = vec![];
+    let search_paths = settings.search_paths(&FileType::Iwad)?;
+    for root in &search_paths.roots {
+        info!("Scanning '{}' for IWADs", root.path.to_string_lossy());
+        let is_wad = |path: &Path| {
+            path.extension()
+                .map(|ext| ext.eq_ignore_ascii_case("wad"))
+                .unwrap_or(false)
+                && search_paths.allows(path, false)
+        };
+        for (path, identity) in index::iwads_under(root, is_wad, identify)? {
+            let game = match identity {
+                IwadIdentity::Iwad(game) => game,
+                IwadIdentity::NotIwad => continue,
+            };
+            trace!(
+                "'{}' is {}",
+                path.to_string_lossy(),
+                game.map(|g| g.to_string())
+                    .unwrap_or_else(|| String::from("an unknown IWAD"))
+            );
+            if !iwads.iter().any(|i: &Iwad| i.path == path) {
+                iwads.push(Iwad { path, game });
+            }
+        }
+    }
+    Ok(iwads)
+}
+
+pub(crate) fn find_iwads_of(game: Game, settings: &Settings) -> Result<Vec<Iwad>, Error> {
+    let (exact, providing): (Vec<_>, Vec<_>) = find_iwads(settings)?
+        .into_iter()
+        .filter(|i| i.game.map(|g| g.provides(game)).unwrap_or(false))
+        .partition(|i| i.game == Some(game));
+    Ok(if exact.is_empty() { providing } else { exact })
+}
+
+pub(crate) fn default_iwad(settings: &Settings) -> Result<Option<Iwad>, Error> {
+    let iwads = find_iwads(settings)?;
+    Ok(iwads
+        .into_iter()
+        .filter(|i| i.game.is_some())
+        .sorted_by_key(|i| {
+            GAME_PREFERENCE
+                .iter()
+                .position(|g| Some(*g) == i.game)
+                .unwrap()
+        })
+        .next())
+}
+
+fn search_iwad_by_name(term: &str, settings: &Settings) -> Result<Option<Iwad>, Error> {
+    if Path::new(term).is_file() {
+        return Iwad::open(term).map(Some);
+    }
+    let found = search_file(term, FileType::Iwad, settings).or_else(|e| {
+        if let Error::FileNotFound(_) = e {
+            Ok(vec![])
+        } else {
+            Err(e)
+        }
+    })?;
+    found.first().map(Iwad::open).transpose()
+}
+
+pub(crate) fn resolve_iwad(term: Option<&str>, settings: &Settings) -> Result<Option<Iwad>, Error> {
+    let term = match term {
+        Some(t) => t,
+        None => return default_iwad(settings),
+    };
+    let game = match Game::from_name(term) {
+        Some(g) => g,
+        None => {
+            let found = search_iwad_by_name(term, settings)?;
+            // A file name like doom2.wad still identifies the game when the IWAD was renamed.
+            let stem_game = Path::new(term)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(Game::from_name);
+            match (found, stem_game) {
+                (Some(iwad), _) => return Ok(Some(iwad)),
+                (None, Some(game)) => game,
+                (None, None) => return Ok(None),
+            }
+        }
+    };
+    let mut candidates = find_iwads_of(game, settings)?;
+    if candidates.is_empty() {
+        warn!(
+            "No IWAD was identified as {}, searching by name instead.",
+            game
+        );
+        return search_iwad_by_name(term, settings);
+    }
+    // A file that is literally named after the game wins, as it did before IWADs were identified.
+    let i = if let Some(i) = candidates.iter().position(|c| {
+        c.path
+            .file_stem()
+            .map(|s| s.eq_ignore_ascii_case(term))
+            .unwrap_or(false)
+    }) {
+        i
+    } else {
+        pins::select_one(
+            &FileType::Iwad,
+            term,
+            &candidates
+                .iter()
+                .map(|c| c.path.clone())
+                .collect::<Vec<_>>(),
+            format!("Multiple IWADs were identified as {}. Select one.", game),
+        )?
+    };
+    Ok(Some(candidates.remove(i)))
+}
+
+pub(crate) struct KnownIwad {
+    md5: &'static str,
+    pub game: Game,
+    pub version: &'static str,
+}
+
+pub(crate) const DEMO_VERSION: &str = "1.9";
+
+const KNOWN_IWADS: &[KnownIwad] = &[
+    KnownIwad {
+        md5: "f0cefca49926d00903cf57551d901abe",
+        game: Game::Shareware,
+        version: "1.9",
+    },
+    KnownIwad {
+        md5: "1cd63c5ddff1bf8ce844237f580e9cf3",
+        game: Game::Registered,
+        version: "1.9",
+    },
+    KnownIwad {
+        md5: "c4fe9fd920207691a9f493668e0a2083",
+        game: Game::Ultimate,
+        version: "1.9",
+    },
+    KnownIwad {
+        md5: "fb35c4a5a9fd49ec29ab6e900572c524",
+        game: Game::Ultimate,
+        version: "BFG Edition",
+    },
+    KnownIwad {
+        md5: "30e3c2d0350b67bfbf47271970b74b2f",
+        game: Game::Doom2,
+        version: "1.666",
+    },
+    KnownIwad {
+        md5: "ea74a47a791fdef2e9f2ea8b8a9da13b",
+        game: Game::Doom2,
+        version: "1.7",
+    },
+    KnownIwad {
+        md5: "d7a07e5d3f4625074312bc299d7ed33f",
+        game: Game::Doom2,
+        version: "1.8",
+    },
+    KnownIwad {
+        md5: "25e1459ca71d321525f84628f45ca8cd",
+        game: Game::Doom2,
+        version: "1.9",
+    },
+    KnownIwad {
+        md5: "c3bea40570c23e511a7ed3ebcd9865f7",
+        game: Game::Doom2,
+        version: "BFG Edition",
+    },
+    KnownIwad {
+        md5: "8ab6d0527a29efdc1ef200e5687b5cae",
+        game: Game::Doom2,
+        version: "Unity",
+    },
+    KnownIwad {
+        md5: "4e158d9953c79ccf97bd0663244cc6b6",
+        game: Game::Tnt,
+        version: "1.9",
+    },
+    KnownIwad {
+        md5: "1d39e405bf6ee3df69a8d2646c8d5c49",
+        game: Game::Tnt,
+        version: "Anthology",
+    },
+    KnownIwad {
+        md5: "75c8cf89566741fa9d22447604053bd7",
+        game: Game::Plutonia,
+        version: "1.9",
+    },
+    KnownIwad {
+        md5: "3493be7e1e2588bc9c8b31eab2587a04",
+        game: Game::Plutonia,
+        version: "Anthology",
+    },
+];
+
+#[derive(Deserialize, Serialize)]
+struct CachedHash {
+    size: u64,
+    mtime: u64,
+    md5: String,
+}
+
+fn cached_md5(path: &Path) -> Result<String, Error> {
+    let cache_path = crate::doom_dir()?.join("iwad_hashes.ron");
+    let mut cache: HashMap<PathBuf, CachedHash> = if cache_path.exists() {
+        // The hashes can always be computed again, so a corrupt cache is started over.
+        ron::from_str(&String::from_utf8_lossy(
+            &std::fs::read(&cache_path).map_err(Error::Io)?,
+        ))
+        .unwrap_or_else(|error| {
+            warn!(
+                "{}; rewriting it.",
+                Error::BadRon {
+                    file: cache_path.clone(),
+                    error,
+                }
+            );
+            HashMap::new()
+        })
+    } else {
+        HashMap::new()
+    };
+
+    let metadata = std::fs::metadata(path).map_err(Error::Io)?;
+    let (size, mtime) = (metadata.len(), modified_secs(&metadata));
+    if let Some(cached) = cache.get(path) {
+        if cached.size == size && cached.mtime == mtime {
+            return Ok(cached.md5.clone());
+        }
+    }
+
+    trace!("Hashing '{}'", path.to_string_lossy());
+    let md5 = format!(
+        "{:x}",
+        md5::compute(std::fs::read(path).map_err(Error::Io)?)
+    );
+    cache.insert(
+        path.to_owned(),
+        CachedHash {
+            size,
+            mtime,
+            md5: md5.clone(),
+        },
+    );
+    std::fs::write(
+        &cache_path,
+        ron::ser::to_string_pretty(&cache, PrettyConfig::default())?,
+    )
+    .map_err(Error::Io)?;
+    Ok(md5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::tests::build_wad;
+    use crate::wad::tests::temp_file;
+
+    fn identify_lumps(name: &str, lumps: &[&str]) -> Option<Game> {
+        let path = temp_file(
+            name,
+            &build_wad(
+                b"IWAD",
+                &lumps
+                    .iter()
+                    .map(|lump| (*lump, &b""[..]))
+                    .collect::<Vec<_>>(),
+            ),
+        );
+        let game = Game::identify(&Wad::open(&path).unwrap());
+        std::fs::remove_file(path).unwrap();
+        game
+    }
+
+    #[test]
+    fn identifies_doom_iwads() {
+        for (lumps, game) in [
+            (&["TITLEPIC", "HELP1", "E1M1"][..], Game::Shareware),
+            (&["TITLEPIC", "HELP1", "E1M1", "E2M1"], Game::Registered),
+            (
+                &["TITLEPIC", "HELP1", "E1M1", "E2M1", "E4M1"],
+                Game::Ultimate,
+            ),
+            (&["TITLEPIC", "HELP", "MAP01"], Game::Doom2),
+            (&["TITLEPIC", "MAP01", "CAVERN5"], Game::Tnt),
+            (&["TITLEPIC", "MAP01", "MC11"], Game::Plutonia),
+            (&["TITLEPIC", "FREEDOOM", "E1M1"], Game::Freedoom1),
+            (&["TITLEPIC", "FREEDOOM", "MAP01"], Game::Freedoom2),
+        ] {
+            assert_eq!(identify_lumps("doom.wad", lumps), Some(game), "{:?}", lumps);
+        }
+    }
+
+    #[test]
+    fn leaves_other_games_unidentified() {
+        for (name, lumps) in [
+            (
+                "heretic.wad",
+                &["TITLE", "ADVISOR", "E1M1", "E2M1", "E3M1"][..],
+            ),
+            ("hexen.wad", &["TITLE", "MAPINFO", "MAP01"]),
+            ("strife1.wad", &["TITLEPIC", "ENDSTRF", "MAP01"]),
+            ("chex.wad", &["TITLEPIC", "HELP1", "W94_1", "E1M1", "E4M1"]),
+            ("unknown.wad", &["E1M1"]),
+        ] {
+            assert_eq!(identify_lumps(name, lumps), None, "{}", name);
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use std::path::PathBuf;
 
+use crate::launch::Launch;
+
 pub(crate) struct Job {
     pub name: String,
     pub demo_name: PathBuf,
-    pub video_name: PathBuf,
+    pub launch: Launch,
 }
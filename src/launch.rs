@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use itertools::Itertools;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cmd::CommandLine;
+use crate::cmd::Line;
+use crate::diagnose;
+use crate::diagnose::LaunchSummary;
+use crate::engine::DoomEngine;
+use crate::error::Error;
+use crate::logs;
+use crate::settings::Settings;
+use crate::util::absolute_path;
+
+#[derive(Deserialize, Serialize)]
+pub(crate) struct SavedLaunch {
+    binary: PathBuf,
+    argv: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    #[serde(default)]
+    working_dir: Option<PathBuf>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Launch {
+    name: String,
+    binary: PathBuf,
+    pub cmdline: CommandLine,
+    env: BTreeMap<String, String>,
+    working_dir: Option<PathBuf>,
+}
+
+fn dirname(binary: &Path) -> PathBuf {
+    let mut d = binary.to_owned();
+    d.pop();
+    d
+}
+
+impl Launch {
+    pub fn new(name: &str, engine: &DoomEngine, cmdline: CommandLine) -> Result<Self, Error> {
+        Ok(Self {
+            name: name.to_owned(),
+            binary: engine.binary.clone(),
+            cmdline,
+            env: engine.env.clone(),
+            working_dir: engine.working_dir.as_ref().map(absolute_path).transpose()?,
+        })
+    }
+
+    pub fn save(&self) -> SavedLaunch {
+        SavedLaunch {
+            binary: self.binary.clone(),
+            argv: self
+                .cmdline
+                .iter_words()
+                .map(|word| word.trim())
+                .filter(|word| !word.is_empty())
+                .map(|word| word.to_owned())
+                .collect(),
+            env: self.env.clone(),
+            working_dir: self.working_dir.clone(),
+        }
+    }
+
+    pub fn restore(name: &str, saved: SavedLaunch) -> Self {
+        let mut cmdline = CommandLine::new();
+        if let Some((program, args)) = saved.argv.split_first() {
+            cmdline.push_line(Line::from_word(program, 0));
+            if !args.is_empty() {
+                cmdline.push_line(Line::from_words(args, 1));
+            }
+        }
+        Self {
+            name: name.to_owned(),
+            binary: saved.binary,
+            cmdline,
+            env: saved.env,
+            working_dir: saved.working_dir,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn working_dir(&self) -> PathBuf {
+        self.working_dir
+            .clone()
+            .unwrap_or_else(|| dirname(&self.binary))
+    }
+
+    pub fn describe_environment(&self) -> Vec<String> {
+        self.env
+            .iter()
+            .map(|(var, value)| format!("Environment: {}={}", var, value))
+            .chain(
+                self.working_dir
+                    .iter()
+                    .map(|dir| format!("Working directory: {}", dir.to_string_lossy())),
+            )
+            .collect()
+    }
+
+    pub fn script(&self) -> String {
+        let cd = self
+            .working_dir
+            .iter()
+            .map(|dir| format!("cd {} && ", shlex::quote(&dir.to_string_lossy())));
+        let env = self
+            .env
+            .iter()
+            .map(|(var, value)| format!("{}={} ", var, shlex::quote(value)));
+        let command = self
+            .cmdline
+            .iter_words()
+            .map(|w| shlex::quote(w.trim()))
+            .join(" ");
+        cd.chain(env).chain(std::iter::once(command)).collect()
+    }
+
+    pub fn command(&self) -> Result<Command, Error> {
+        if !self.binary.exists() {
+            return Err(Error::FileNotFound(
+                self.binary.to_string_lossy().into_owned(),
+            ));
+        }
+        let mut words = self.cmdline.iter_words().filter_map(|arg| {
+            let trimmed = arg.trim();
+            if trimmed.is_empty() {
+                None
+            } else {
+                Some(trimmed)
+            }
+        });
+        let program = words
+            .next()
+            .ok_or_else(|| Error::EmptyCommandLine(self.name.clone()))?;
+        let mut command = Command::new(program);
+        command
+            .args(words)
+            .envs(&self.env)
+            .current_dir(self.working_dir());
+        Ok(command)
+    }
+
+    pub fn supervise(&self, interactive: bool, settings: &Settings) -> Result<Session, Error> {
+        let mut command = self.command()?;
+        if interactive {
+            let mut child = command.spawn().map_err(Error::RunningDoom)?;
+            let _forwarding = signals::Forwarding::start(child.id());
+            let status = child.wait().map_err(Error::RunningDoom)?;
+            return Ok(Session { status, log: None });
+        }
+
+        let log_path = logs::new_session_log(&self.name, settings)?;
+        let log = Arc::new(Mutex::new(File::create(&log_path).map_err(Error::Io)?));
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(Error::RunningDoom)?;
+        let _forwarding = signals::Forwarding::start(child.id());
+        let tee = |mut from: Box<dyn Read + Send>, mut to: Box<dyn Write + Send>| {
+            let log = Arc::clone(&log);
+            std::thread::spawn(move || {
+                let mut buf = [0; 4096];
+                while let Ok(n) = from.read(&mut buf) {
+                    if n == 0 {
+                        break;
+                    }
+                    let _ = to.write_all(&buf[..n]);
+                    let _ = to.flush();
+                    if let Ok(mut log) = log.lock() {
+                        let _ = log.write_all(&buf[..n]);
+                    }
+                }
+            })
+        };
+        let readers = [
+            child
+                .stdout
+                .take()
+                .map(|out| tee(Box::new(out), Box::new(std::io::stdout()))),
+            child
+                .stderr
+                .take()
+                .map(|err| tee(Box::new(err), Box::new(std::io::stderr()))),
+        ];
+        let status = child.wait().map_err(Error::RunningDoom)?;
+        for reader in readers.into_iter().flatten() {
+            let _ = reader.join();
+        }
+        Ok(Session {
+            status,
+            log: Some(log_path),
+        })
+    }
+
+    pub fn run(&self, interactive: bool, settings: &Settings) -> Result<(), Error> {
+        self.supervise(interactive, settings)?.check(&self.name)
+    }
+
+    pub fn play(&self, summary: &LaunchSummary, settings: &Settings) -> Result<(), Error> {
+        let session = self.supervise(false, settings)?;
+        match &session.log {
+            Some(log) if !session.status.success() => diagnose::report(log, summary),
+            _ => {}
+        }
+        session.check(&self.name)
+    }
+}
+
+pub(crate) struct Session {
+    pub status: ExitStatus,
+    pub log: Option<PathBuf>,
+}
+
+impl Session {
+    pub fn check(&self, engine_name: &str) -> Result<(), Error> {
+        if self.status.success() {
+            return Ok(());
+        }
+        let log = self
+            .log
+            .as_ref()
+            .map(|log| format!("; its output is in '{}'", log.to_string_lossy()))
+            .unwrap_or_default();
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = self.status.signal() {
+                // Ctrl-C and SIGTERM stop the engine on purpose; any other signal is a crash.
+                let log = match &self.log {
+                    Some(log) if !signals::FORWARDED.contains(&signal) => format!(
+                        "; its output is kept in '{}'",
+                        logs::keep_as_crash_log(log)?.to_string_lossy()
+                    ),
+                    _ => log,
+                };
+                return Err(Error::EngineKilled {
+                    engine: engine_name.to_owned(),
+                    signal,
+                    log,
+                });
+            }
+        }
+        Err(Error::EngineFailed {
+            engine: engine_name.to_owned(),
+            code: self.status.code().unwrap_or(-1),
+            log,
+        })
+    }
+}
+
+#[cfg(unix)]
+mod signals {
+    use std::sync::atomic::AtomicI32;
+    use std::sync::atomic::Ordering;
+
+    static CHILD: AtomicI32 = AtomicI32::new(0);
+
+    pub(crate) const FORWARDED: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+    extern "C" fn forward(
+        signal: libc::c_int,
+        info: *mut libc::siginfo_t,
+        _context: *mut libc::c_void,
+    ) {
+        // Signals from the terminal, such as Ctrl-C, reach the engine by themselves since it is
+        // in the same process group; only those another process sent to the launcher are passed
+        // on.
+        let sent_by_process = unsafe { (*info).si_code } <= 0;
+        let child = CHILD.load(Ordering::SeqCst);
+        if sent_by_process && child > 0 {
+            unsafe {
+                libc::kill(child, signal);
+            }
+        }
+    }
+
+    pub(crate) struct Forwarding {
+        previous: Vec<(libc::c_int, libc::sigaction)>,
+    }
+
+    impl Forwarding {
+        pub fn start(child: u32) -> Self {
+            CHILD.store(child as i32, Ordering::SeqCst);
+            let previous = FORWARDED
+                .iter()
+                .filter_map(|&signal| unsafe {
+                    let mut action: libc::sigaction = std::mem::zeroed();
+                    action.sa_sigaction = forward as *const () as usize;
+                    action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+                    libc::sigemptyset(&mut action.sa_mask);
+                    let mut previous: libc::sigaction = std::mem::zeroed();
+                    (libc::sigaction(signal, &action, &mut previous) == 0)
+                        .then_some((signal, previous))
+                })
+                .collect();
+            Self { previous }
+        }
+    }
+
+    impl Drop for Forwarding {
+        fn drop(&mut self) {
+            for (signal, previous) in &self.previous {
+                unsafe {
+                    libc::sigaction(*signal, previous, std::ptr::null_mut());
+                }
+            }
+            CHILD.store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod signals {
+    pub(crate) struct Forwarding;
+
+    impl Forwarding {
+        pub fn start(_child: u32) -> Self {
+            Self
+        }
+    }
+}
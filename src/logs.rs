@@ -0,0 +1,68 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use log::info;
+
+use crate::error::Error;
+use crate::settings::Settings;
+use crate::util::modified_secs;
+use crate::util::now_secs;
+
+const DEFAULT_KEEP_LOGS: usize = 20;
+
+const CRASH_SUFFIX: &str = "-crash.log";
+
+fn logs_dir() -> Result<PathBuf, Error> {
+    let logs = crate::doom_dir()?.join("logs");
+    std::fs::create_dir_all(&logs).map_err(Error::Io)?;
+    Ok(logs)
+}
+
+fn rotate(logs: &Path, keep: usize) -> Result<(), Error> {
+    let mut sessions = std::fs::read_dir(logs)
+        .map_err(Error::Io)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.ends_with(".log") && !name.ends_with(CRASH_SUFFIX)
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((modified_secs(&metadata), entry.path()))
+        })
+        .collect::<Vec<_>>();
+    sessions.sort();
+    let excess = (sessions.len() + 1).saturating_sub(keep.max(1));
+    for (_, old) in sessions.into_iter().take(excess) {
+        info!("Removing old log '{}'", old.to_string_lossy());
+        std::fs::remove_file(&old).map_err(Error::Io)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn new_session_log(engine_name: &str, settings: &Settings) -> Result<PathBuf, Error> {
+    let logs = logs_dir()?;
+    rotate(&logs, settings.keep_logs.unwrap_or(DEFAULT_KEEP_LOGS))?;
+    let secs = now_secs();
+    let mut log = logs.join(format!("{}-{}.log", engine_name, secs));
+    let mut n = 1;
+    while log.exists() || crash_log_path(&log).exists() {
+        n += 1;
+        log = logs.join(format!("{}-{}-{}.log", engine_name, secs, n));
+    }
+    Ok(log)
+}
+
+fn crash_log_path(log: &Path) -> PathBuf {
+    log.with_file_name(format!(
+        "{}{}",
+        log.file_stem().unwrap_or_default().to_string_lossy(),
+        CRASH_SUFFIX
+    ))
+}
+
+pub(crate) fn keep_as_crash_log(log: &Path) -> Result<PathBuf, Error> {
+    let crash_log = crash_log_path(log);
+    std::fs::rename(log, &crash_log).map_err(Error::Io)?;
+    Ok(crash_log)
+}
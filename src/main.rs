@@ -2,13 +2,12 @@ use std::fs::create_dir_all;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::exit;
-use std::process::Command;
-use std::str::FromStr;
 use std::sync::Mutex;
 
 use clap::App;
 use clap::AppSettings;
 use clap::Arg;
+use clap::ArgMatches;
 use clap::ColorChoice;
 use dialoguer::console::style;
 use dialoguer::theme::ColorfulTheme;
@@ -21,29 +20,77 @@ use log::info;
 use log::warn;
 use once_cell::sync::Lazy;
 
+use crate::args::geometry_values;
+use crate::args::EngineArgs;
+use crate::args::Opt;
 use crate::cmd::CommandLine;
 use crate::cmd::Line;
+use crate::complevel::complevel_values;
+use crate::complevel::demo_iwad_version;
+use crate::complevel::resolve_complevel;
+use crate::debugger::find_debugger;
+use crate::debugger::Debugger;
+use crate::demo::Demo;
+use crate::diagnose::LaunchSummary;
 use crate::engine::read_known_engines;
+use crate::engine::DoomEngine;
 use crate::engine::DoomEngineKind;
+use crate::engine::KnownEngines;
+use crate::engine::ENGINE_KINDS;
 use crate::error::Error;
+use crate::iwad::resolve_iwad;
+use crate::iwad::Iwad;
+use crate::iwad::DEMO_VERSION;
+use crate::launch::Launch;
+use crate::maps::requested_map;
+use crate::maps::warp_values;
+use crate::maps::Maps;
+use crate::pwads::add_recorded_pwads;
 use crate::pwads::parse_arg_pwads;
 use crate::pwads::parse_extra_pwads;
 use crate::pwads::Pwads;
 use crate::render::batch_render;
-use crate::util::absolute_path;
-
+use crate::render::render_job;
+use crate::settings::Settings;
+use crate::sidecar::Sidecar;
+use crate::skill::default_skill;
+use crate::skill::resolve_skill;
+use crate::skill::skill_values;
+use crate::state::LauncherState;
+use crate::util::recorded_file_name;
+
+mod args;
 mod autoload;
 mod cmd;
+mod complevel;
+mod debugger;
+mod demo;
+mod diagnose;
 mod engine;
 mod error;
+mod history;
+mod index;
+mod iwad;
 mod job;
+mod launch;
+mod logs;
+mod mapinfo;
+mod maps;
+mod pins;
+mod probe;
+mod profile;
 mod pwads;
 mod render;
 mod score;
 mod search;
+mod settings;
+mod sidecar;
+mod skill;
+mod state;
 mod util;
+mod wad;
 
-static CUSTOM_DOOM_DIR: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+static CUSTOM_DOOM_DIR: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(stored_doom_dir()));
 
 enum FileType {
     Iwad,
@@ -51,14 +98,6 @@ enum FileType {
     Demo,
 }
 
-impl FileType {
-    fn get_search_dirs(&self) -> Result<Vec<PathBuf>, Error> {
-        vec![doom_dir(), Ok(public_doom_dir())]
-            .into_iter()
-            .collect()
-    }
-}
-
 const ARG_SEPARATOR: char = ',';
 
 fn home_dir() -> Result<PathBuf, Error> {
@@ -73,6 +112,16 @@ fn doom_dir() -> Result<PathBuf, Error> {
     }
 }
 
+fn stored_doom_dir() -> Option<PathBuf> {
+    match LauncherState::read() {
+        Ok(state) => state.doom_dir,
+        Err(e) => {
+            warn!("{}; ignoring the remembered Doom directory.", e);
+            None
+        }
+    }
+}
+
 fn public_doom_dir() -> PathBuf {
     PathBuf::from("/public/doom")
 }
@@ -86,88 +135,116 @@ fn dump_dir() -> Result<PathBuf, Error> {
 }
 
 fn select_between<P: AsRef<Path>>(
+    ty: &FileType,
     search: impl AsRef<str>,
     options: impl AsRef<[P]>,
 ) -> Result<Vec<PathBuf>, Error> {
+    let options = options
+        .as_ref()
+        .iter()
+        .map(|opt| opt.as_ref().to_owned())
+        .collect::<Vec<_>>();
+    if let Some(pinned) = pins::pinned_among(ty, search.as_ref(), &options)? {
+        return Ok(pinned);
+    }
+    let selected = choose_between(search.as_ref(), &options)?;
+    if !selected.is_empty() {
+        pins::pin(ty, search.as_ref(), &selected)?;
+    }
+    Ok(selected)
+}
+
+// Unlike `select_between`, neither looks at nor adds pins.
+fn choose_between(search: &str, options: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
     MultiSelect::new()
-        .with_prompt(format!("Multiple files were found for the search term {}. Please select one or more of the following:", search.as_ref()))
+        .with_prompt(format!("Multiple files were found for the search term {}. Please select one or more of the following:", search))
         .items(
             &options
-                .as_ref()
                 .iter()
-                .map(|opt| opt.as_ref().to_string_lossy())
+                .map(|opt| opt.to_string_lossy())
                 .collect::<Vec<_>>(),
         )
         .interact()
-        .map(|indices| indices.iter().map(|i| options.as_ref()[*i].as_ref().to_owned()).collect())
+        .map(|indices| indices.iter().map(|i| options[*i].clone()).collect::<Vec<_>>())
         .map_err(Error::Io)
 }
 
-fn run_doom<'l>(mut cmdline: impl Iterator<Item = &'l str>) -> Result<(), Error> {
-    let binary = PathBuf::from(cmdline.next().unwrap());
-    if !binary.exists() {
-        return Err(Error::FileNotFound(binary.to_string_lossy().into_owned()));
-    }
-    let binary_dir = dirname(&binary);
-    let args = cmdline
-        .filter_map(|arg| {
-            let trimmed = arg.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(trimmed)
-            }
-        })
-        .collect::<Vec<_>>();
-    Command::new(binary)
-        .args(args)
-        .current_dir(binary_dir)
-        .status()
-        .map(|_| ())
-        .map_err(Error::RunningDoom)
-}
-
-fn dirname(binary: &Path) -> PathBuf {
-    let mut d = binary.to_owned();
-    d.pop();
-    d
-}
-
 fn run() -> Result<(), Error> {
     let app = App::new("Command-line Doom launcher")
             .version(clap::crate_version!())
             .before_help("This Doom launcher allows shortcuts to the many long-winded options that Doom engines accept.")
             .setting(AppSettings::TrailingVarArg)
+            .args_override_self(true)
             .color(ColorChoice::Auto)
-            .arg(Arg::new("compatibility-level").short('c').long("compatibility-level").help("Set the compatibility level to LEVEL").value_name("LEVEL"))
-            .arg(Arg::new("debug").short('G').long("debug").help("Run Doom under a debugger"))
-            .arg(Arg::new("doom-dir").long("doom-dir").help("Set a custom Doom configuration directory"))
-            .arg(Arg::new("engine").short('e').long("engine").help("Play the game with ENGINE instead of DSDA Doom").value_name("ENGINE"))
-            .arg(Arg::new("extra-pwads").short('x').long("extra-pwads").help("Add PWADS to the game, silently").long_help("Silently means that when rendering a demo (with --render), the program will not add these PWADs to the folder name.").value_name("WAD").multiple_values(true))
+            .arg(Arg::new("compatibility-level").short('c').long("compatibility-level").help("Set the compatibility level to LEVEL").long_help("Set the compatibility level to LEVEL, either a number or one of: vanilla (picked from the IWAD), doom-1.2, doom-1.666, doom2-1.9, ultimate, final, dosdoom, tasdoom, boom-2.01, boom, lxdoom, mbf, mbf21, mbf21-ext. Negative numbers such as -1 are passed on as they are. Chocolate and Crispy Doom get the matching -gameversion instead.").value_name("LEVEL").allow_hyphen_values(true))
+            .arg(Arg::new("debug").short('G').long("debug").help("Run Doom under a debugger").long_help("Run Doom under DEBUGGER: lldb, gdb, rr, valgrind or one from the `debuggers` in settings.ron. Without a name, `default_debugger` from settings.ron is used, or else the first of them that is installed.").value_name("DEBUGGER").min_values(0).max_values(1).require_equals(true))
+            .arg(Arg::new("debug-batch").long("debug-batch").help("Run Doom under a debugger without interaction, saving its output to ~/doom/logs if Doom crashes").long_help("Run Doom under the debugger chosen with --debug (or the default one) without interaction: it prints a backtrace if Doom crashes, and its output is then saved to logs/ in your Doom directory."))
+            .arg(Arg::new("doom-dir").long("doom-dir").help("Set a custom Doom configuration directory").long_help("Set a custom Doom configuration directory. It is remembered for later runs until another one is set or --reset-doom-dir is passed.").value_name("DIR").takes_value(true))
+            .arg(Arg::new("engine").short('e').long("engine").help("Play the game with ENGINE instead of the default engine").long_help("Play the game with ENGINE, by name or alias, instead of the engine marked `default: true` in engines.ron (or the only engine defined there).").value_name("ENGINE"))
+            .arg(Arg::new("extra-pwads").short('x').long("extra-pwads").help("Add PWADS to the game, silently").long_help("Silently means that when rendering a demo (with --render), the program will not add these PWADs to the folder name. Can be given more than once; the PWADs are added in order.").value_name("WAD").multiple_values(true).multiple_occurrences(true))
             .arg(Arg::new("fast").short('f').long("fast").help("Enable fast monsters"))
-            .arg(Arg::new("geometry").short('g').long("geometry").help("Set the screen resolution to WxH").long_help("Set the screen resolution to WxH; only supported on Boom-derived sourceports.").value_name("GEOM"))
-            .arg(Arg::new("iwad").short('i').long("iwad").help("Set the game's IWAD").value_name("WAD"))
+            .arg(Arg::new("geometry").short('g').long("geometry").help("Set the screen resolution to WxH").long_help("Set the screen resolution to WxH. Translated to each engine's own arguments, such as -width and -height for ZDoom.").value_name("GEOM"))
+            .arg(Arg::new("iwad").short('i').long("iwad").help("Set the game's IWAD").long_help("Set the game's IWAD, either by file name or by game: doom1, doom, doomu, doom2, tnt, plutonia, freedoom1 or freedoom2. Without this flag, the IWADs in your search directories are identified by their contents and Doom II is preferred.").value_name("WAD"))
             .arg(Arg::new("no-confirm").long("no-confirm").short('n').help("Don't ask for confirmation before running Doom"))
             .arg(Arg::new("no-monsters").long("no-monsters").help("Play the game with no monsters"))
-            .arg(Arg::new("pistol-start").long("pistol-start").help("Play each level from a pistol start").long_help("Play each level from a pistol start. Currently only works with Crispy Doom and PrBoom+."))
-            .arg(Arg::new("play-demo").short('d').long("play-demo").help("Play back DEMO").value_name("DEMO"))
-            .arg(Arg::new("pwads").short('p').long("pwads").help("Add PWADS to the game").multiple_values(true).value_name("WAD"))
-            .arg(Arg::new("record").short('r').long("record").help("Record a demo to DEMO").value_name("DEMO").long_help("Record a demo to DEMO, relative to ~/doom/demo."))
+            .arg(Arg::new("pistol-start").long("pistol-start").help("Play each level from a pistol start").long_help("Play each level from a pistol start. Boom-derived sourceports support it out of the box; for others, such as Crispy Doom, add it to the engine's `args` in engines.ron."))
+            .arg(Arg::new("play-demo").short('d').long("play-demo").help("Play back DEMO").long_help("Play back DEMO. Unless overridden, the complevel is taken from the demo's header, and if the chosen engine cannot play the demo's format, another engine that can is used. The IWAD and PWADs recorded in a PrBoom+ or DSDA-Doom demo footer are loaded automatically.").value_name("DEMO"))
+            .arg(Arg::new("pwads").short('p').long("pwads").help("Add PWADS to the game").long_help("Add PWADS to the game, separated by commas. Can be given more than once; the PWADs are loaded in order.").multiple_values(true).multiple_occurrences(true).value_name("WAD"))
+            .arg(Arg::new("record").short('r').long("record").help("Record a demo to DEMO").value_name("DEMO").long_help("Record a demo to DEMO, relative to ~/doom/demo. The resolved engine, IWAD, PWADs, DEHACKED patches and complevel are saved next to it in DEMO.lmp.ron, which --play-demo and --render read back; the demo itself records the skill, map and flags."))
             .arg(Arg::new("record-from-to").long("record-from-to").number_of_values(2).help("Play back FROM, allowing you to rewrite its ending to TO").long_help("Play FROM. You are allowed to press the join key at any time to begin recording your inputs from the current frame. Whenever you quit the game, the final result will be written to TO.").value_names(&["FROM", "TO"]))
-            .arg(Arg::new("render").short('R').long("render").help("Render a demo as a video").long_help("The video will be placed in /extra/Videos/{iwad}/{pwads}/{demoname}.").value_name("DEMO"))
+            .arg(Arg::new("render").short('R').long("render").help("Render a demo as a video").long_help("The video will be placed in /extra/Videos/{iwad}/{pwads}/{demoname}. As with --play-demo, the IWAD and PWADs recorded in the demo's footer are loaded automatically.").value_name("DEMO"))
+            .arg(Arg::new("reset-doom-dir").long("reset-doom-dir").help("Forget the remembered Doom directory and go back to ~/doom").conflicts_with("doom-dir"))
             .arg(Arg::new("respawn").long("respawn").help("Enable respawning monsters"))
             .arg(Arg::new("script").long("script").help("Generate a shell script").long_help("Generate a shell script that will run the same command as this program. Writes to stdout."))
             .arg(Arg::new("short-tics").long("short-tics").help("Play the game with short tics instead of long tics"))
-            .arg(Arg::new("skill").short('s').long("skill").help("Set the game's skill level by a number").value_name("SKILL"))
-            .arg(Arg::new("video-mode").short('v').long("video-mode").help("Set the video mode of the game (software, hardware)").long_help("Only supported on Boom-derived sourceports.").value_name("MODE"))
-            .arg(Arg::new("warp").short('w').long("warp").help("Start the game at a specific level number").value_name("LEVEL"))
-            .arg(Arg::new("passthrough").multiple_values(true))
+            .arg(Arg::new("skill").short('s').long("skill").help("Set the game's skill level").long_help("Set the game's skill level, from 1 to 5 as in the skill menu, or by name: itytd, hntr, hmp, uv or nm. It is converted for engines that count differently, like ZDoom. When warping without this flag, the engine's `default_skill` from engines.ron is used, else the `default_skill` from settings.ron, else UV.").value_name("SKILL"))
+            .arg(Arg::new("video-mode").short('v').long("video-mode").help("Set the video mode of the game (software, hardware)").long_help("Only supported on Boom-derived sourceports, unless an engine's `args` in engines.ron say otherwise.").value_name("MODE"))
+            .arg(Arg::new("warp").short('w').long("warp").help("Start the game at a specific level number").long_help("Start the game at LEVEL, given either as a map name (MAP07, E2M3) or as the numbers -warp takes (7, or 2,3 for episodic IWADs). The map must exist in the loaded IWAD or PWADs. Without LEVEL, or with '?', pick the map from a list.").value_name("LEVEL").min_values(0).max_values(1))
+            .arg(Arg::new("passthrough").multiple_values(true).help("Arguments passed on to the engine, or @NAME to use a profile from profiles.ron first").value_name("ARGS"))
+            .subcommand(App::new("demo").about("Examine demos without launching Doom").subcommand_required(true).subcommand(App::new("info").about("Describe the header and length of demos").arg(Arg::new("demos").required(true).multiple_values(true).help("Paths to demos or folders of demos, or search terms").value_name("DEMO"))))
+            .subcommand(App::new("engines").about("Manage the engines in engines.ron").subcommand_required(true)
+                .subcommand(App::new("list").about("List every engine with its aliases, kind and binary"))
+                .subcommand(App::new("show").about("Describe one engine").arg(Arg::new("engine").required(true).help("Name or alias of the engine").value_name("ENGINE")))
+                .subcommand(App::new("add").about("Add an engine to engines.ron, keeping its comments")
+                    .arg(Arg::new("name").required(true).help("Canonical name of the engine").value_name("NAME"))
+                    .arg(Arg::new("binary").required(true).help("Path to the engine's executable, or its name in PATH").value_name("BINARY"))
+                    .arg(Arg::new("kind").long("kind").short('k').required(true).possible_values(ENGINE_KINDS).ignore_case(true).help("Which compatibility levels the engine supports").value_name("KIND"))
+                    .arg(Arg::new("alias").long("alias").short('a').multiple_occurrences(true).help("Another name to use with -e").value_name("ALIAS"))
+                    .arg(Arg::new("required-arg").long("required-arg").multiple_occurrences(true).allow_hyphen_values(true).help("An argument to always pass to the engine").value_name("ARG"))
+                    .arg(Arg::new("merge").long("merge").help("Load PWADs and DEHACKED patches with -merge"))
+                    .arg(Arg::new("default").long("default").help("Use this engine when -e is not given")))
+                .subcommand(App::new("remove").about("Remove an engine from engines.ron").arg(Arg::new("engine").required(true).help("Name or alias of the engine").value_name("ENGINE")))
+                .subcommand(App::new("check").about("Check that every binary is executable and that no name refers to two engines")))
+            .subcommand(App::new("history").about("List recent launches, most recent first").arg(Arg::new("count").long("count").short('n').help("How many launches to list").value_name("COUNT").default_value("10").validator(|n| n.parse::<usize>())))
+            .subcommand(App::new("again").about("Launch a recent session again, exactly as before").arg(Arg::new("n").help("Which launch, as numbered by `playdoom history`").value_name("N").default_value("1").validator(|n| n.parse::<usize>())))
+            .subcommand(App::new("pins").about("Manage the answers remembered when a search term matched several files").subcommand_required(true)
+                .subcommand(App::new("list").about("List every pinned search term and the files it stands for"))
+                .subcommand(App::new("forget").about("Forget pinned search terms, so that playdoom asks again")
+                    .arg(Arg::new("terms").multiple_values(true).required_unless_present("all").help("The search terms to forget").value_name("TERM"))
+                    .arg(Arg::new("all").long("all").conflicts_with("terms").help("Forget every pinned search term"))))
+            .subcommand(App::new("index").about("Manage the index of your search directories").subcommand_required(true).subcommand(App::new("rebuild").about("Throw the index away and list every search directory again")))
+            .subcommand(App::new("inspect").about("List the lumps and maps in a WAD").arg(Arg::new("file").required(true).help("Path to, or search term for, the WAD").value_name("FILE")))
             ;
 
-    let matches = app.get_matches();
+    let matches = app.clone().get_matches();
 
-    if let Some(doom_dir) = matches.value_of("doom-dir") {
-        *CUSTOM_DOOM_DIR.lock().unwrap() = Some(PathBuf::from_str(doom_dir).unwrap());
+    let new_doom_dir = matches
+        .value_of("doom-dir")
+        .map(|dir| std::env::current_dir().map(|cwd| cwd.join(dir)))
+        .transpose()
+        .map_err(Error::Io)?;
+    if let Some(dir) = &new_doom_dir {
+        *CUSTOM_DOOM_DIR.lock().unwrap() = Some(dir.clone());
+    }
+    if matches.is_present("reset-doom-dir") {
+        *CUSTOM_DOOM_DIR.lock().unwrap() = None;
+        let mut state = LauncherState::read()?;
+        state.doom_dir = None;
+        state.write()?;
+        info!(
+            "Forgot the Doom directory; using {}.",
+            doom_dir()?.to_string_lossy()
+        );
     }
 
     if !doom_dir()?.exists() {
@@ -186,53 +263,296 @@ fn run() -> Result<(), Error> {
             return Ok(());
         }
     }
+    if let Some(dir) = new_doom_dir {
+        let mut state = LauncherState::read()?;
+        state.doom_dir = Some(dir);
+        state.write()?;
+    }
+    let settings = Settings::read()?;
+
+    match matches.subcommand() {
+        Some(("demo", demo_matches)) => {
+            if let Some(("info", info_matches)) = demo_matches.subcommand() {
+                return demo::info(
+                    &info_matches.values_of("demos").unwrap().collect_vec(),
+                    &settings,
+                );
+            }
+        }
+        Some(("engines", engines_matches)) => match engines_matches.subcommand() {
+            Some(("list", _)) => return engine::list(),
+            Some(("show", show_matches)) => {
+                return engine::show(show_matches.value_of("engine").unwrap())
+            }
+            Some(("add", add_matches)) => {
+                let name = add_matches.value_of("name").unwrap();
+                let binary = PathBuf::from(add_matches.value_of("binary").unwrap());
+                // A bare name is kept as it is, to be looked up in PATH.
+                let binary = if binary.components().count() > 1 {
+                    std::env::current_dir().map_err(Error::Io)?.join(binary)
+                } else {
+                    binary
+                };
+                let engine = DoomEngine::new(
+                    add_matches
+                        .values_of("alias")
+                        .map(|aliases| aliases.map(|a| a.to_owned()).collect())
+                        .unwrap_or_default(),
+                    binary,
+                    DoomEngineKind::from_name(add_matches.value_of("kind").unwrap()).unwrap(),
+                    add_matches
+                        .values_of("required-arg")
+                        .map(|args| args.map(|a| a.to_owned()).collect())
+                        .unwrap_or_default(),
+                    add_matches.is_present("merge"),
+                    add_matches.is_present("default"),
+                );
+                return engine::add(name, engine);
+            }
+            Some(("remove", remove_matches)) => {
+                return engine::remove(remove_matches.value_of("engine").unwrap())
+            }
+            Some(("check", _)) => return engine::check(),
+            _ => {}
+        },
+        Some(("history", history_matches)) => {
+            return history::list(history_matches.value_of_t_or_exit("count"));
+        }
+        Some(("again", again_matches)) => {
+            return history::again(again_matches.value_of_t_or_exit("n"), &settings);
+        }
+        Some(("pins", pins_matches)) => match pins_matches.subcommand() {
+            Some(("list", _)) => return pins::list(),
+            Some(("forget", forget_matches)) => {
+                return pins::forget(
+                    &forget_matches
+                        .values_of("terms")
+                        .map(|terms| terms.collect_vec())
+                        .unwrap_or_default(),
+                )
+            }
+            _ => {}
+        },
+        Some(("index", index_matches)) => {
+            if let Some(("rebuild", _)) = index_matches.subcommand() {
+                return index::rebuild(&settings);
+            }
+        }
+        Some(("inspect", inspect_matches)) => {
+            return wad::inspect(inspect_matches.value_of("file").unwrap(), &settings);
+        }
+        _ => {}
+    }
+
+    let matches = match profile::requested(&matches) {
+        Some(requested) => app.get_matches_from(profile::expand(&requested, std::env::args_os())?),
+        None => matches,
+    };
 
+    let playing_demo = matches
+        .value_of("play-demo")
+        .map(|playing_demo| {
+            let demo = select_between(
+                &FileType::Demo,
+                playing_demo,
+                search::search_demo(playing_demo, &settings)?,
+            )?;
+            if demo.is_empty() {
+                error!("No such demo: {}", playing_demo);
+                exit(-1);
+            }
+            Ok::<_, Error>(demo[0].clone())
+        })
+        .transpose()?;
+    let render_demos = matches
+        .value_of("render")
+        .map(|demos| render::find_demos(demos, &settings))
+        .transpose()?;
     let known_engines = read_known_engines()?;
-    let engine_name = matches
+
+    if let Some(render_demos) = render_demos {
+        // Each demo gets its own launch, from its own header, footer and sidecar.
+        return batch_render(
+            render_demos,
+            |demo| {
+                let prepared = prepare_launch(&matches, &settings, &known_engines, Some(&demo))?;
+                render_job(
+                    demo,
+                    &prepared.launch,
+                    &prepared.engine_args,
+                    &prepared.render_dir,
+                )
+            },
+            &settings,
+        );
+    }
+
+    let prepared = prepare_launch(&matches, &settings, &known_engines, playing_demo.as_deref())?;
+    let launch = &prepared.launch;
+    if matches.is_present("script") {
+        println!("{}", launch.script());
+    } else {
+        eprintln!();
+        if let Some(release) = prepared.iwad.describe() {
+            eprintln!("IWAD: {}", release);
+        }
+        for line in launch.describe_environment() {
+            eprintln!("{}", line);
+        }
+        eprintln!(
+            "Command line: \n'\n{}\n'",
+            launch
+                .cmdline
+                .iter_lines()
+                .map(|l| l.iter().join(" "))
+                .join("\n")
+        );
+        if !matches.is_present("no-confirm") {
+            Input::<String>::with_theme(&ColorfulTheme {
+                prompt_prefix: style("*".into()).yellow(),
+                ..Default::default()
+            })
+            .with_prompt("Press enter to launch Doom.")
+            .allow_empty(true)
+            .interact()
+            .map_err(Error::Io)?;
+        }
+        if let (Some(demo_path), Some(sidecar)) = (&prepared.recording_demo, &prepared.sidecar) {
+            sidecar.write_for(demo_path)?;
+        }
+        match &prepared.debugger {
+            Some(debugger) if matches.is_present("debug-batch") => {
+                debugger.run_batch(launch, &settings)?
+            }
+            Some(_) => launch.run(true, &settings)?,
+            None => {
+                let summary = LaunchSummary {
+                    kind: prepared.kind,
+                    iwad: &prepared.iwad,
+                    pwads: &prepared.pwads,
+                    warp: prepared.warp_map.as_deref(),
+                };
+                launch.play(&summary, &settings)?;
+                history::record(launch, &summary, prepared.recording_demo.as_deref());
+            }
+        }
+    }
+    Ok(())
+}
+
+struct PreparedLaunch {
+    kind: DoomEngineKind,
+    engine_args: EngineArgs,
+    launch: Launch,
+    iwad: Iwad,
+    pwads: Pwads,
+    warp_map: Option<String>,
+    recording_demo: Option<PathBuf>,
+    sidecar: Option<Sidecar>,
+    debugger: Option<Debugger>,
+    render_dir: PathBuf,
+}
+
+fn prepare_launch(
+    matches: &ArgMatches,
+    settings: &Settings,
+    known_engines: &KnownEngines,
+    demo_path: Option<&Path>,
+) -> Result<PreparedLaunch, Error> {
+    let demo = demo_path.and_then(|path| match Demo::open(path) {
+        Ok(demo) => Some(demo),
+        Err(e) => {
+            warn!("{}; playback will not be configured from the demo.", e);
+            None
+        }
+    });
+    let sidecar = demo_path.map(Sidecar::read_for).transpose()?.flatten();
+
+    let sidecar_engine = sidecar
+        .as_ref()
+        .map(|sidecar| sidecar.engine.clone())
+        .filter(|name| {
+            let known = known_engines.get(name).is_some();
+            if !known {
+                warn!("The demo was recorded with the unknown engine '{}'.", name);
+            }
+            known
+        });
+    let engine_chosen = matches.is_present("engine") || sidecar_engine.is_some();
+    let mut engine_name = match matches
         .value_of("engine")
         .map(|s| s.to_owned())
-        .or_else(|| known_engines.iter().next())
-        .ok_or(Error::NoEngines)?;
-    let engine = &known_engines.get(&engine_name).unwrap_or_else(|| {
+        .or(sidecar_engine)
+    {
+        Some(name) => name,
+        None => known_engines.default_name()?.to_owned(),
+    };
+    let mut engine = known_engines.get(&engine_name).unwrap_or_else(|| {
         error!("ERROR: Unknown sourceport '{}'", engine_name);
         exit(-1);
     });
-
-    let mut search_iwads: Box<dyn Iterator<Item = String>> = matches
-        .value_of("iwad")
-        .map::<Box<dyn Iterator<Item = String>>, _>(|i| Box::new(std::iter::once(i.to_string())))
-        .unwrap_or_else(|| {
-            Box::new(
-                ["DOOM2.WAD", "DOOM.WAD", "DOOMU.WAD", "DOOM1.WAD"]
-                    .iter()
-                    .map(|i: &&str| i.to_string()),
-            )
-        });
-    let iwad_path = loop {
-        let iwad = match search_iwads.next() {
-            Some(i) => i,
-            None => break None,
-        };
-        let iwad_path = search::search_file(&iwad, FileType::Iwad).or_else(|e| {
-            if let Error::FileNotFound(_) = e {
-                Ok(vec![])
-            } else {
-                Err(e)
+    if let Some(demo) = &demo {
+        let format = demo.header.format;
+        if !format.playable_by(engine.kind) {
+            // The plainest kind of engine that can play the demo, then the first by name.
+            let replacement = known_engines
+                .iter()
+                .filter(|(_, e)| format.playable_by(e.kind))
+                .min_by_key(|(name, e)| (e.kind as u8, *name));
+            match replacement {
+                Some((name, replacement)) if !engine_chosen => {
+                    warn!(
+                        "'{}' cannot play {} demos, using '{}' instead.",
+                        engine_name, format, name
+                    );
+                    engine = replacement;
+                    engine_name = name.to_owned();
+                }
+                _ => warn!(
+                    "'{}' cannot play {} demos; expect a desync.",
+                    engine_name, format
+                ),
             }
-        })?;
-        if iwad_path.is_empty() {
-            warn!("IWAD not found: '{}'", iwad);
-        } else {
-            break Some(iwad_path);
+        }
+        for (flag, recorded, engine_flag) in [
+            ("fast", demo.header.fast, "-fast"),
+            ("respawn", demo.header.respawn, "-respawn"),
+            ("no-monsters", demo.header.nomonsters, "-nomonsters"),
+        ] {
+            if matches.is_present(flag) && !recorded {
+                return Err(Error::DemoConflict {
+                    demo: demo.path.clone(),
+                    flag: engine_flag,
+                });
+            }
+        }
+    }
+
+    let footer = demo.as_ref().and_then(|demo| demo.footer.as_ref());
+    let footer_iwad = footer
+        .and_then(|footer| footer.iwad.as_deref())
+        .map(recorded_file_name);
+    let sidecar_iwad = sidecar
+        .as_ref()
+        .map(|sidecar| sidecar.iwad.to_string_lossy().into_owned());
+    let iwad = match resolve_iwad(
+        matches
+            .value_of("iwad")
+            .or(sidecar_iwad.as_deref())
+            .or(footer_iwad),
+        settings,
+    )? {
+        Some(iwad) => iwad,
+        None => {
+            error!("No IWADs could be found.");
+            exit(-1);
         }
     };
-    if iwad_path.is_none() {
-        error!("No IWADs could be found.");
-        exit(-1);
+    if let Some(game) = iwad.game {
+        info!("Using {} IWAD '{}'", game, iwad.path.to_string_lossy());
     }
-    let iwad_path = iwad_path.unwrap();
-    let iwad_path = absolute_path(&iwad_path[0])?;
-    let iwad = iwad_path.to_string_lossy().to_string();
+    let iwad_path = iwad.path.clone();
+    let iwad_str = iwad_path.to_string_lossy().to_string();
 
     let iwad_base = iwad_path
         .file_name()
@@ -241,18 +561,33 @@ fn run() -> Result<(), Error> {
             f.to_str()
                 .ok_or_else(|| Error::NonUtf8Path(f.to_string_lossy().into_owned()))
         })?;
-    let iwad_noext = iwad_path
-        .file_stem()
-        .ok_or_else(|| Error::NoFileStem(iwad_path.to_string_lossy().into_owned()))
-        .and_then(|i| {
-            i.to_str()
-                .ok_or_else(|| Error::NonUtf8Path(i.to_string_lossy().into_owned()))
-        })?
-        .to_lowercase();
+    let iwad_noext = match iwad.game {
+        Some(game) => game.canonical_stem().to_owned(),
+        None => iwad_path
+            .file_stem()
+            .ok_or_else(|| Error::NoFileStem(iwad_path.to_string_lossy().into_owned()))
+            .and_then(|i| {
+                i.to_str()
+                    .ok_or_else(|| Error::NonUtf8Path(i.to_string_lossy().into_owned()))
+            })?
+            .to_lowercase(),
+    };
 
+    let engine_args = EngineArgs::new(&engine_name, engine);
     let mut cmdline = CommandLine::new();
-    if matches.is_present("debug") {
-        cmdline.push_line(Line::from_word("/usr/bin/lldb", 0));
+    for wrapper in &engine.wrappers {
+        cmdline.push_line(Line::from_word(wrapper, 0));
+    }
+    let debug_batch = matches.is_present("debug-batch");
+    let debugger = if matches.is_present("debug") || debug_batch {
+        let (debugger_name, debugger) = find_debugger(matches.value_of("debug"), settings)?;
+        info!("Debugging with {}", debugger_name);
+        Some(debugger)
+    } else {
+        None
+    };
+    if let Some(debugger) = &debugger {
+        debugger.push_command(&mut cmdline, debug_batch);
     }
     cmdline.push_line(Line::from_word(
         engine
@@ -261,26 +596,59 @@ fn run() -> Result<(), Error> {
             .ok_or_else(|| Error::NonUtf8Path(engine.binary.to_string_lossy().into_owned()))?,
         0,
     ));
-    if matches.is_present("debug") {
-        cmdline.push_line(Line::from_word("--", 0));
+    if let Some(debugger) = &debugger {
+        debugger.push_separator(&mut cmdline);
     }
     if !engine.required_args.is_empty() {
         cmdline.push_line(Line::from_words(&engine.required_args, 1));
     }
-    cmdline.push_line(Line::from_words(&["-iwad", &iwad], 1));
+    cmdline.push_line(Line::from_words(&["-iwad", &iwad_str], 1));
 
     let mut pwads = Pwads::new();
 
-    autoload::autoload(&mut pwads, &engine.binary, &iwad_noext)?;
+    autoload::autoload(&mut pwads, &engine.binary, &iwad_noext, settings)?;
 
     let mut viddump_folder_name = vec![];
 
-    if let Some(arg_pwads_raw) = matches.value_of("pwads") {
-        parse_arg_pwads(arg_pwads_raw, &mut viddump_folder_name, &mut pwads)?;
+    if let Some(sidecar) = &sidecar {
+        let to_strings = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect_vec()
+        };
+        add_recorded_pwads(
+            &to_strings(&sidecar.pwads),
+            &to_strings(&sidecar.dehs),
+            &mut viddump_folder_name,
+            &mut pwads,
+            settings,
+        )?;
+    } else if let Some(footer) = footer {
+        add_recorded_pwads(
+            &footer.files,
+            &footer.dehs,
+            &mut viddump_folder_name,
+            &mut pwads,
+            settings,
+        )?;
     }
 
-    if let Some(extra_pwads_raw) = matches.value_of("extra-pwads") {
-        parse_extra_pwads(extra_pwads_raw, &mut pwads)?;
+    if let Some(arg_pwads) = matches.values_of("pwads") {
+        parse_arg_pwads(
+            &arg_pwads.collect_vec().join(&ARG_SEPARATOR.to_string()),
+            &mut viddump_folder_name,
+            &mut pwads,
+            settings,
+        )?;
+    }
+
+    if let Some(extra_pwads) = matches.values_of("extra-pwads") {
+        parse_extra_pwads(
+            &extra_pwads.collect_vec().join(&ARG_SEPARATOR.to_string()),
+            &mut pwads,
+            settings,
+        )?;
     }
 
     if !pwads.wads().is_empty() {
@@ -314,98 +682,154 @@ fn run() -> Result<(), Error> {
         })?;
     }
 
-    if let Some(complevel) = matches.value_of("compatibility-level") {
-        cmdline.push_line(Line::from_words(
-            &[String::from("-complevel"), complevel.to_string()],
-            1,
-        ));
+    let demo_complevel = demo
+        .as_ref()
+        .and_then(|demo| demo.header.complevel_for(iwad.game));
+    let requested_complevel = matches
+        .value_of("compatibility-level")
+        .or_else(|| sidecar.as_ref().and_then(|s| s.complevel.as_deref()))
+        .map(|complevel| resolve_complevel(complevel, engine.kind, iwad.game))
+        .transpose()?;
+    let complevel = match (requested_complevel, demo_complevel) {
+        (Some(complevel), Some(recorded)) => {
+            if complevel != recorded {
+                warn!(
+                    "The demo was recorded at complevel {}, but complevel {} was requested.",
+                    recorded, complevel
+                );
+            }
+            Some(complevel)
+        }
+        (Some(complevel), None) => Some(complevel),
+        (None, Some(recorded))
+            if matches!(engine.kind, DoomEngineKind::Boom | DoomEngineKind::MBF) =>
+        {
+            info!("Playing the demo back at complevel {}", recorded);
+            Some(recorded)
+        }
+        (None, _) => None,
+    };
+
+    let demo_version = match complevel {
+        Some(complevel) => demo_iwad_version(complevel),
+        None if engine.kind == DoomEngineKind::Vanilla => Some(DEMO_VERSION),
+        None => None,
+    };
+    if let (Some(version), true) = (
+        demo_version,
+        matches.is_present("play-demo") || matches.is_present("record"),
+    ) {
+        match iwad.release() {
+            Some(known) if known.version == version => {}
+            Some(known) => warn!(
+                "The IWAD is {} {}; vanilla demos at this complevel only stay in sync with v{} IWADs.",
+                known.game, known.version, version
+            ),
+            None => warn!(
+                "The IWAD '{}' is not a known release; vanilla demos at this complevel only stay in sync with v{} IWADs.",
+                iwad_str, version
+            ),
+        }
+    }
+
+    if let Some(complevel) = complevel {
+        engine_args.push(&mut cmdline, Opt::Complevel, &complevel_values(complevel))?;
     }
 
     if matches.is_present("pistol-start") {
-        cmdline.push_line(Line::from_word("-pistolstart", 1));
+        engine_args.push_flag(&mut cmdline, Opt::PistolStart)?;
     }
 
     if let Some(vidmode) = matches.value_of("video-mode") {
-        cmdline.push_line(Line::from_words(&["-vidmode", vidmode], 1));
+        engine_args.push(&mut cmdline, Opt::VideoMode, &[("value", vidmode)])?;
     }
 
     if let Some(geom) = matches.value_of("geometry") {
-        cmdline.push_line(Line::from_words(&["-geom", geom], 1));
+        engine_args.push(&mut cmdline, Opt::Geometry, &geometry_values(geom))?;
     }
 
-    let skill_param = if engine.kind == DoomEngineKind::ZDoom {
-        &["+skill", "3"]
-    } else {
-        &["-skill", "4"]
-    };
+    let recording_demo = matches
+        .value_of("record")
+        .map(|recording_demo| {
+            let demo_path = PathBuf::from(recording_demo);
+            if demo_path.is_absolute() {
+                Ok(demo_path)
+            } else {
+                demo_dir().map(|d| d.join(demo_path))
+            }
+        })
+        .transpose()?;
 
-    if let Some(recording_demo) = matches.value_of("record") {
-        let demo_path = PathBuf::from(recording_demo);
-        let demo_path = if demo_path.is_absolute() {
-            demo_path
-        } else {
-            demo_dir()?.join(demo_path)
-        };
-        cmdline.push_line(Line::from_word("-record", 1));
-        cmdline.push_line(Line::from_word(demo_path.to_string_lossy(), 2));
-        if !matches.is_present("short-tics") {
-            cmdline.push_line(Line::from_word("-longtics", 1));
+    if let Some(demo_path) = &recording_demo {
+        engine_args.push(
+            &mut cmdline,
+            Opt::Record,
+            &[("value", demo_path.to_string_lossy())],
+        )?;
+        // Long tics are only a default, so engines that cannot record them just don't.
+        if !matches.is_present("short-tics") && engine_args.supports(Opt::LongTics) {
+            engine_args.push_flag(&mut cmdline, Opt::LongTics)?;
         }
     } else if matches.is_present("short-tics") {
-        cmdline.push_line(Line::from_word("-shorttics", 1));
+        engine_args.push_flag(&mut cmdline, Opt::ShortTics)?;
     }
 
     if let Some(from_to) = matches.values_of("record-from-to") {
         let from_to = from_to.collect::<Vec<_>>();
-        cmdline.push_line(Line::from_word("-recordfromto", 1));
-        cmdline.push_line(Line::from_words(&from_to[0..2], 2));
+        engine_args.push(
+            &mut cmdline,
+            Opt::RecordFromTo,
+            &[("from", from_to[0]), ("to", from_to[1])],
+        )?;
     }
 
-    if let Some(playing_demo) = matches.value_of("play-demo") {
-        let demo = select_between(
-            playing_demo,
-            search::search_file(playing_demo, FileType::Demo)?,
+    if let Some(playing_demo) = demo_path.filter(|_| matches.is_present("play-demo")) {
+        engine_args.push(
+            &mut cmdline,
+            Opt::PlayDemo,
+            &[(
+                "value",
+                playing_demo.to_str().ok_or_else(|| {
+                    Error::NonUtf8Path(playing_demo.to_string_lossy().into_owned())
+                })?,
+            )],
         )?;
-        if demo.is_empty() {
-            error!("No such demo: {}", playing_demo);
-            exit(-1);
-        }
-        cmdline.push_line(Line::from_word("-playdemo", 1));
-        cmdline.push_line(Line::from_word(
-            demo[0]
-                .to_str()
-                .ok_or_else(|| Error::NonUtf8Path(demo[0].to_string_lossy().into_owned()))?,
-            2,
-        ));
     }
 
-    if let Some(warp) = matches.value_of("warp") {
-        cmdline.push_line(Line::from_words(
-            &{
-                let mut words = vec!["-warp"];
-                words.extend(warp.split(ARG_SEPARATOR));
-                words
-            },
-            1,
-        ));
-    }
+    let warp_map = if matches.is_present("warp") {
+        let maps = Maps::collect(&iwad_path, iwad.game, &pwads)?;
+        let map = match requested_map(matches.value_of("warp")) {
+            Some(warp) => maps.resolve_warp(warp)?,
+            None => maps.pick()?,
+        };
+        engine_args.push(&mut cmdline, Opt::Warp, &warp_values(&map))?;
+        Some(map)
+    } else {
+        None
+    };
 
-    if let Some(skill) = matches.value_of("skill") {
-        cmdline.push_line(Line::from_words(&[skill_param[0], skill], 1));
-    } else if matches.is_present("warp") {
-        cmdline.push_line(Line::from_words(skill_param, 1));
+    let skill = match matches.value_of("skill") {
+        Some(skill) => Some(resolve_skill(skill)?),
+        None if warp_map.is_some() => Some(default_skill(
+            engine.default_skill.as_deref(),
+            settings.default_skill.as_deref(),
+        )?),
+        None => None,
+    };
+    if let Some(skill) = skill {
+        engine_args.push(&mut cmdline, Opt::Skill, &skill_values(skill, engine.kind))?;
     }
 
     if matches.is_present("no-monsters") {
-        cmdline.push_line(Line::from_word("-nomonsters", 1));
+        engine_args.push_flag(&mut cmdline, Opt::NoMonsters)?;
     }
 
     if matches.is_present("fast") {
-        cmdline.push_line(Line::from_word("-fast", 1));
+        engine_args.push_flag(&mut cmdline, Opt::Fast)?;
     }
 
     if matches.is_present("respawn") {
-        cmdline.push_line(Line::from_word("-respawn", 1));
+        engine_args.push_flag(&mut cmdline, Opt::Respawn)?;
     }
 
     if let Some(passthrough) = matches.values_of("passthrough") {
@@ -414,39 +838,46 @@ fn run() -> Result<(), Error> {
         }
     }
 
-    if let Some(render_matches) = matches.value_of("render") {
-        let dump_dir = dump_dir()?
-            .join(iwad_base)
-            .join(viddump_folder_name.join(","));
-        let renderings = render::collect_renderings(render_matches, &dump_dir)?;
-        batch_render(renderings, &cmdline, dump_dir)?;
-    } else if matches.is_present("script") {
-        println!(
-            "{}",
+    let record_sidecar = recording_demo.as_ref().map(|_| Sidecar {
+        engine: engine_name.clone(),
+        iwad: iwad_path.clone(),
+        pwads: pwads.wads().to_vec(),
+        dehs: pwads.dehs().to_vec(),
+        complevel: complevel.map(|c| c.to_string()),
+    });
+
+    let render_args = if matches.is_present("render") {
+        engine_args.translate(Opt::VidDump, &[("value", "")])?
+    } else {
+        vec![]
+    };
+    if !matches.is_present("script") {
+        probe::warn_unsupported(
+            &engine_name,
+            engine.kind,
+            &engine.binary,
             cmdline
                 .iter_words()
-                .map(|w| shlex::quote(w.trim()))
-                .join(" ")
-        );
-    } else {
-        eprintln!();
-        eprintln!(
-            "Command line: \n'\n{}\n'",
-            cmdline.iter_lines().map(|l| l.iter().join(" ")).join("\n")
+                .chain(render_args.iter().map(|a| a.as_str())),
         );
-        if !matches.is_present("no-confirm") {
-            Input::<String>::with_theme(&ColorfulTheme {
-                prompt_prefix: style("*".into()).yellow(),
-                ..Default::default()
-            })
-            .with_prompt("Press enter to launch Doom.")
-            .allow_empty(true)
-            .interact()
-            .map_err(Error::Io)?;
-        }
-        run_doom(cmdline.iter_words())?;
     }
-    Ok(())
+
+    let launch = Launch::new(&engine_name, engine, cmdline)?;
+    let render_dir = dump_dir()?
+        .join(iwad_base)
+        .join(viddump_folder_name.join(","));
+    Ok(PreparedLaunch {
+        kind: engine.kind,
+        engine_args,
+        launch,
+        iwad,
+        pwads,
+        warp_map,
+        recording_demo,
+        sidecar: record_sidecar,
+        debugger,
+        render_dir,
+    })
 }
 
 fn main() {
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use crate::iwad::Game;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Symbol(char),
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '"' => {
+                let mut s = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => s.extend(chars.next()),
+                        c => s.push(c),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '{' | '}' | '=' | ',' => tokens.push(Token::Symbol(c)),
+            c => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}=,\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    tokens
+}
+
+fn is_keyword(token: &Token, keyword: &str) -> bool {
+    matches!(token, Token::Word(w) if w.eq_ignore_ascii_case(keyword))
+}
+
+pub(crate) fn parse_umapinfo(text: &str) -> HashMap<String, String> {
+    let tokens = tokenize(text);
+    let mut names = HashMap::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if let (true, Some(Token::Word(map))) = (is_keyword(&tokens[i], "map"), tokens.get(i + 1)) {
+            let map = map.to_uppercase();
+            i += 2;
+            let mut depth = 0;
+            while i < tokens.len() {
+                match &tokens[i] {
+                    Token::Symbol('{') => depth += 1,
+                    Token::Symbol('}') => {
+                        depth -= 1;
+                        if depth <= 0 {
+                            break;
+                        }
+                    }
+                    t if depth == 1 && is_keyword(t, "levelname") => {
+                        if let (Some(Token::Symbol('=')), Some(Token::Str(name))) =
+                            (tokens.get(i + 1), tokens.get(i + 2))
+                        {
+                            names.insert(map.clone(), name.clone());
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+    names
+}
+
+pub(crate) fn parse_mapinfo(text: &str) -> HashMap<String, String> {
+    tokenize(text)
+        .windows(3)
+        .filter_map(|w| match w {
+            [map, Token::Word(name), Token::Str(title)] if is_keyword(map, "map") => {
+                Some((name.to_uppercase(), title.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn mnemonic_map(mnemonic: &str, game: Option<Game>) -> Option<String> {
+    let mnemonic = mnemonic.to_uppercase();
+    if let Some(episode_map) = mnemonic.strip_prefix("HUSTR_E") {
+        return Some(format!("E{}", episode_map));
+    }
+    let prefix = match game {
+        Some(Game::Tnt) => "THUSTR_",
+        Some(Game::Plutonia) => "PHUSTR_",
+        _ => "HUSTR_",
+    };
+    mnemonic
+        .strip_prefix(prefix)
+        .and_then(|n| n.parse::<u32>().ok())
+        .map(|n| format!("MAP{:02}", n))
+}
+
+pub(crate) fn parse_dehacked(text: &str, game: Option<Game>) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    let mut in_strings = false;
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_strings = line.eq_ignore_ascii_case("[STRINGS]");
+            continue;
+        }
+        if !in_strings || line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some(kv) => kv,
+            None => {
+                // Any other block header, such as `Thing 1`, ends the section.
+                in_strings = false;
+                continue;
+            }
+        };
+        let mut value = value.trim().to_owned();
+        while value.ends_with('\\') {
+            value.pop();
+            match lines.next() {
+                Some(next) => value.push_str(next.trim()),
+                None => break,
+            }
+        }
+        if let Some(map) = mnemonic_map(key.trim(), game) {
+            names.insert(map, value);
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn umapinfo_level_names() {
+        let names = parse_umapinfo(
+            r#"
+            // A comment with map MAP99 { levelname = "Nope" }
+            map MAP01 { levelname = "Entry \"way\"" next = "MAP02" }
+            /* map MAP03 */
+            MAP map02
+            {
+                levelname = "Underhalls"
+                bossaction = clear
+            }
+            "#,
+        );
+        assert_eq!(names.len(), 2);
+        assert_eq!(names["MAP01"], "Entry \"way\"");
+        assert_eq!(names["MAP02"], "Underhalls");
+    }
+
+    #[test]
+    fn mapinfo_level_names() {
+        let names = parse_mapinfo(
+            r#"
+            map MAP01 "Entryway" { next = "MAP02" }
+            map map02 lookup "HUSTR_2" { }
+            map E1M1 "Hangar"
+            "#,
+        );
+        assert_eq!(names.len(), 2);
+        assert_eq!(names["MAP01"], "Entryway");
+        assert_eq!(names["E1M1"], "Hangar");
+    }
+
+    #[test]
+    fn dehacked_level_names() {
+        let text = "Patch File for DeHackEd v3.0\n\
+                    [STRINGS]\n\
+                    HUSTR_1 = level 1: a long \\\n  name\n\
+                    HUSTR_E1M2 = Nuclear Plant\n\
+                    PHUSTR_3 = Plutonia 3\n\
+                    Thing 1 (Zombieman)\n\
+                    HUSTR_4 = not a string\n";
+        let names = parse_dehacked(text, None);
+        assert_eq!(names.len(), 2);
+        assert_eq!(names["MAP01"], "level 1: a long name");
+        assert_eq!(names["E1M2"], "Nuclear Plant");
+        assert_eq!(
+            parse_dehacked(text, Some(Game::Plutonia))["MAP03"],
+            "Plutonia 3"
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use log::info;
+use log::warn;
+
+use crate::error::Error;
+use crate::iwad::Game;
+use crate::mapinfo::parse_dehacked;
+use crate::mapinfo::parse_mapinfo;
+use crate::mapinfo::parse_umapinfo;
+use crate::pwads::Pwads;
+use crate::wad::Wad;
+use crate::ARG_SEPARATOR;
+
+pub(crate) struct Maps {
+    names: Vec<String>,
+    commercial: bool,
+    // False when some loaded file could not be read, so a missing map may still exist.
+    complete: bool,
+    titles: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct Titles {
+    umapinfo: HashMap<String, String>,
+    mapinfo: HashMap<String, String>,
+    dehacked: HashMap<String, String>,
+}
+
+impl Titles {
+    fn read(&mut self, wad: &Wad, game: Option<Game>) -> Result<(), Error> {
+        if let Some(lump) = wad.read_lump("UMAPINFO")? {
+            self.umapinfo
+                .extend(parse_umapinfo(&String::from_utf8_lossy(&lump)));
+        }
+        for name in ["MAPINFO", "ZMAPINFO"] {
+            if let Some(lump) = wad.read_lump(name)? {
+                self.mapinfo
+                    .extend(parse_mapinfo(&String::from_utf8_lossy(&lump)));
+            }
+        }
+        if let Some(lump) = wad.read_lump("DEHACKED")? {
+            self.dehacked
+                .extend(parse_dehacked(&String::from_utf8_lossy(&lump), game));
+        }
+        Ok(())
+    }
+
+    fn merge(self) -> HashMap<String, String> {
+        let mut titles = self.dehacked;
+        titles.extend(self.mapinfo);
+        titles.extend(self.umapinfo);
+        titles
+    }
+}
+
+fn episode_map(name: &str) -> Option<(u32, u32)> {
+    let bytes = name.as_bytes();
+    if bytes.len() == 4
+        && bytes[0].eq_ignore_ascii_case(&b'E')
+        && bytes[2].eq_ignore_ascii_case(&b'M')
+        && bytes[1].is_ascii_digit()
+        && bytes[3].is_ascii_digit()
+    {
+        Some(((bytes[1] - b'0') as u32, (bytes[3] - b'0') as u32))
+    } else {
+        None
+    }
+}
+
+fn map_number(name: &str) -> Option<u32> {
+    if name.len() == 5
+        && name.is_ascii()
+        && name[0..3].eq_ignore_ascii_case("MAP")
+        && name[3..].bytes().all(|b| b.is_ascii_digit())
+    {
+        name[3..].parse().ok()
+    } else {
+        None
+    }
+}
+
+pub(crate) fn is_warpable(name: &str) -> bool {
+    episode_map(name).is_some() || map_number(name).is_some()
+}
+
+impl Maps {
+    pub fn collect(iwad: &Path, game: Option<Game>, pwads: &Pwads) -> Result<Self, Error> {
+        let mut maps = Self {
+            names: vec![],
+            commercial: true,
+            complete: true,
+            titles: HashMap::new(),
+        };
+        let mut titles = Titles::default();
+        match Wad::open(iwad) {
+            Ok(wad) => {
+                let iwad_maps = wad.maps();
+                maps.commercial = !iwad_maps.iter().any(|m| episode_map(m).is_some());
+                maps.add(iwad_maps);
+            }
+            Err(e) => {
+                warn!("Cannot read the maps in the IWAD: {}", e);
+                maps.complete = false;
+            }
+        }
+        for wad in pwads.wads() {
+            let is_wad = wad
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("wad"))
+                .unwrap_or(false);
+            if !is_wad || !wad.is_file() {
+                info!(
+                    "Cannot list the maps in '{}', so they will not be checked.",
+                    wad.to_string_lossy()
+                );
+                maps.complete = false;
+                continue;
+            }
+            match Wad::open(wad) {
+                Ok(wad) => {
+                    maps.add(wad.maps());
+                    if let Err(e) = titles.read(&wad, game) {
+                        warn!(
+                            "Cannot read the level names in '{}': {}",
+                            wad.path.to_string_lossy(),
+                            e
+                        );
+                    }
+                }
+                Err(e) => {
+                    warn!("{}", e);
+                    maps.complete = false;
+                }
+            }
+        }
+        for deh in pwads.dehs() {
+            match std::fs::read(deh) {
+                Ok(text) => titles
+                    .dehacked
+                    .extend(parse_dehacked(&String::from_utf8_lossy(&text), game)),
+                Err(e) => warn!(
+                    "Cannot read the level names in '{}': {}",
+                    deh.to_string_lossy(),
+                    e
+                ),
+            }
+        }
+        maps.titles = titles.merge();
+        Ok(maps)
+    }
+
+    fn add(&mut self, names: Vec<String>) {
+        for name in names {
+            if !self.names.contains(&name) {
+                self.names.push(name);
+            }
+        }
+    }
+
+    pub fn resolve_warp(&self, warp: &str) -> Result<String, Error> {
+        let no_such_map = |map: String| Error::NoSuchMap {
+            map,
+            valid: self
+                .names
+                .iter()
+                .filter(|n| is_warpable(n))
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        let name = if is_warpable(warp) {
+            warp.to_uppercase()
+        } else {
+            let numbers = warp
+                .split(ARG_SEPARATOR)
+                .map(|n| n.trim().parse::<u32>().ok())
+                .collect::<Option<Vec<_>>>();
+            match (self.commercial, numbers.as_deref()) {
+                (true, Some([map])) => format!("MAP{:02}", map),
+                // Vanilla treats a lone number as the episode and starts on its first map.
+                (false, Some([episode])) => format!("E{}M1", episode),
+                (false, Some([episode, map])) => format!("E{}M{}", episode, map),
+                _ => return Err(no_such_map(warp.to_owned())),
+            }
+        };
+        if self.complete && !self.names.contains(&name) {
+            return Err(no_such_map(name));
+        }
+        Ok(name)
+    }
+
+    pub fn pick(&self) -> Result<String, Error> {
+        let warpable = self
+            .names
+            .iter()
+            .filter(|n| is_warpable(n))
+            .collect::<Vec<_>>();
+        if warpable.is_empty() {
+            return Err(Error::NoMaps);
+        }
+        let i = dialoguer::Select::new()
+            .items(
+                &warpable
+                    .iter()
+                    .map(|n| match self.titles.get(*n) {
+                        Some(title) => format!("{:<5}  {}", n, title),
+                        None => n.to_string(),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .with_prompt("Select a map to start on.")
+            .default(0)
+            .interact()
+            .map_err(Error::Io)?;
+        Ok(warpable[i].clone())
+    }
+}
+
+pub(crate) fn requested_map(warp: Option<&str>) -> Option<&str> {
+    warp.filter(|warp| *warp != "?")
+}
+
+pub(crate) fn warp_values(name: &str) -> Vec<(&'static str, String)> {
+    let mut values = vec![("name", name.to_owned())];
+    if let Some((episode, map)) = episode_map(name) {
+        values.push(("episode", episode.to_string()));
+        values.push(("map", map.to_string()));
+    } else if let Some(map) = map_number(name) {
+        // Leaves `{episode}` out of `-warp {episode} {map}`.
+        values.push(("episode", String::new()));
+        values.push(("map", map.to_string()));
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loaded(names: Vec<String>) -> Maps {
+        Maps {
+            commercial: !names.iter().any(|m| episode_map(m).is_some()),
+            names,
+            complete: true,
+            titles: HashMap::new(),
+        }
+    }
+
+    fn doom2() -> Maps {
+        loaded((1..=32).map(|n| format!("MAP{:02}", n)).collect())
+    }
+
+    fn ultimate() -> Maps {
+        loaded(
+            (1..=4)
+                .flat_map(|e| (1..=9).map(move |m| format!("E{}M{}", e, m)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn warps_by_name() {
+        assert_eq!(doom2().resolve_warp("MAP07").unwrap(), "MAP07");
+        assert_eq!(doom2().resolve_warp("map07").unwrap(), "MAP07");
+        assert_eq!(ultimate().resolve_warp("E2M3").unwrap(), "E2M3");
+    }
+
+    #[test]
+    fn warps_by_number() {
+        assert_eq!(doom2().resolve_warp("7").unwrap(), "MAP07");
+        assert_eq!(ultimate().resolve_warp("2,3").unwrap(), "E2M3");
+        assert_eq!(ultimate().resolve_warp("2").unwrap(), "E2M1");
+    }
+
+    #[test]
+    fn picks_without_a_map_or_with_question_mark() {
+        assert_eq!(requested_map(None), None);
+        assert_eq!(requested_map(Some("?")), None);
+        assert_eq!(requested_map(Some("7")), Some("7"));
+    }
+
+    #[test]
+    fn rejects_maps_that_are_not_loaded() {
+        for (maps, warp) in [
+            (doom2(), "MAP33"),
+            (doom2(), "33"),
+            (doom2(), "1,9"),
+            (doom2(), "E1M1"),
+            (doom2(), "start"),
+            (ultimate(), "5,1"),
+            (ultimate(), "MAP01"),
+            (ultimate(), "1,2,3"),
+        ] {
+            match maps.resolve_warp(warp) {
+                Err(Error::NoSuchMap { valid, .. }) => {
+                    assert!(valid.starts_with(&maps.names[0]), "{}", warp);
+                    assert_eq!(valid.split(", ").count(), maps.names.len(), "{}", warp);
+                }
+                other => panic!(
+                    "{} resolved to {:?}",
+                    warp,
+                    other.map_err(|e| e.to_string())
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use log::info;
+use log::warn;
+use ron::ser::PrettyConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::FileType;
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Pins {
+    iwads: BTreeMap<String, Vec<PathBuf>>,
+    pwads: BTreeMap<String, Vec<PathBuf>>,
+    demos: BTreeMap<String, Vec<PathBuf>>,
+}
+
+fn pins_path() -> Result<PathBuf, Error> {
+    Ok(crate::doom_dir()?.join("pins.ron"))
+}
+
+impl Pins {
+    fn read() -> Result<Self, Error> {
+        let path = pins_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        ron::from_str(&String::from_utf8_lossy(
+            &std::fs::read(&path).map_err(Error::Io)?,
+        ))
+        .map_err(|error| Error::BadRon { file: path, error })
+    }
+
+    fn write(&self) -> Result<(), Error> {
+        std::fs::write(
+            pins_path()?,
+            ron::ser::to_string_pretty(self, PrettyConfig::default())?,
+        )
+        .map_err(Error::Io)
+    }
+
+    fn of_type(&mut self, ty: &FileType) -> &mut BTreeMap<String, Vec<PathBuf>> {
+        match ty {
+            FileType::Iwad => &mut self.iwads,
+            FileType::Pwad => &mut self.pwads,
+            FileType::Demo => &mut self.demos,
+        }
+    }
+
+    fn forget_gone(&mut self, ty: &FileType, term: &str) -> bool {
+        let gone = match self
+            .of_type(ty)
+            .get(term)
+            .and_then(|pinned| pinned.iter().find(|path| !path.exists()))
+        {
+            Some(gone) => gone.clone(),
+            None => return false,
+        };
+        warn!(
+            "'{}' was chosen for '{}' before, but it is gone; choose again.",
+            gone.to_string_lossy(),
+            term
+        );
+        self.of_type(ty).remove(term);
+        true
+    }
+
+    fn pinned_among(
+        &mut self,
+        ty: &FileType,
+        term: &str,
+        candidates: &[PathBuf],
+    ) -> Option<Vec<PathBuf>> {
+        self.of_type(ty)
+            .get(term)
+            .filter(|pinned| pinned.iter().all(|path| candidates.contains(path)))
+            .cloned()
+    }
+}
+
+pub(crate) fn pinned_among(
+    ty: &FileType,
+    term: &str,
+    candidates: &[PathBuf],
+) -> Result<Option<Vec<PathBuf>>, Error> {
+    let mut pins = Pins::read()?;
+    if pins.forget_gone(ty, term) {
+        pins.write()?;
+        return Ok(None);
+    }
+    Ok(pins.pinned_among(ty, term, candidates))
+}
+
+pub(crate) fn pin(ty: &FileType, term: &str, paths: &[PathBuf]) -> Result<(), Error> {
+    let mut pins = Pins::read()?;
+    pins.of_type(ty).insert(term.to_owned(), paths.to_vec());
+    pins.write()?;
+    info!(
+        "Remembering the choice for '{}'; `playdoom pins forget {}` makes playdoom ask again.",
+        term, term
+    );
+    Ok(())
+}
+
+pub(crate) fn select_one(
+    ty: &FileType,
+    term: &str,
+    candidates: &[PathBuf],
+    prompt: impl Into<String>,
+) -> Result<usize, Error> {
+    if candidates.len() <= 1 {
+        return Ok(0);
+    }
+    if let Some(pinned) = pinned_among(ty, term, candidates)? {
+        if let Some(i) = candidates.iter().position(|c| pinned.first() == Some(c)) {
+            return Ok(i);
+        }
+    }
+    let i = dialoguer::Select::new()
+        .items(
+            &candidates
+                .iter()
+                .map(|c| c.to_string_lossy())
+                .collect::<Vec<_>>(),
+        )
+        .with_prompt(prompt)
+        .interact()
+        .map_err(Error::Io)?;
+    pin(ty, term, &candidates[i..=i])?;
+    Ok(i)
+}
+
+pub(crate) fn list() -> Result<(), Error> {
+    let mut pins = Pins::read()?;
+    let mut any = false;
+    for (ty, name) in [
+        (FileType::Iwad, "IWAD"),
+        (FileType::Pwad, "PWAD"),
+        (FileType::Demo, "demo"),
+    ] {
+        for (term, paths) in pins.of_type(&ty).iter() {
+            any = true;
+            println!(
+                "{:<5} {}  ->  {}",
+                name,
+                term,
+                paths.iter().map(|p| p.to_string_lossy()).join(", ")
+            );
+        }
+    }
+    if !any {
+        println!("Nothing is pinned.");
+    }
+    Ok(())
+}
+
+pub(crate) fn forget(terms: &[&str]) -> Result<(), Error> {
+    let mut pins = Pins::read()?;
+    if terms.is_empty() {
+        pins = Pins::default();
+    }
+    for term in terms {
+        let mut found = false;
+        for ty in [FileType::Iwad, FileType::Pwad, FileType::Demo] {
+            found |= pins.of_type(&ty).remove(*term).is_some();
+        }
+        if !found {
+            warn!("Nothing is pinned for '{}'.", term);
+        }
+    }
+    pins.write()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::tests::temp_file;
+
+    fn pins(term: &str, pinned: Vec<PathBuf>) -> Pins {
+        let mut pins = Pins::default();
+        pins.pwads.insert(term.to_owned(), pinned);
+        pins
+    }
+
+    #[test]
+    fn pinned_among_candidates() {
+        let first = temp_file("pins-first.wad", b"PWAD");
+        let second = temp_file("pins-second.wad", b"PWAD");
+        let mut pins = pins("pins", vec![first.clone()]);
+        assert!(!pins.forget_gone(&FileType::Pwad, "pins"));
+        assert_eq!(
+            pins.pinned_among(&FileType::Pwad, "pins", &[first.clone(), second.clone()]),
+            Some(vec![first.clone()])
+        );
+        // Pins are kept per type of file and term.
+        assert_eq!(
+            pins.pinned_among(&FileType::Iwad, "pins", std::slice::from_ref(&first)),
+            None
+        );
+        assert_eq!(
+            pins.pinned_among(&FileType::Pwad, "other", std::slice::from_ref(&first)),
+            None
+        );
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn pinned_file_no_longer_a_candidate() {
+        let first = temp_file("pins-moved.wad", b"PWAD");
+        let second = temp_file("pins-other.wad", b"PWAD");
+        let mut pins = pins("pins", vec![first.clone()]);
+        assert!(!pins.forget_gone(&FileType::Pwad, "pins"));
+        assert_eq!(
+            pins.pinned_among(&FileType::Pwad, "pins", std::slice::from_ref(&second)),
+            None
+        );
+        // The pin stays, for when the file is found again.
+        assert_eq!(
+            pins.pinned_among(&FileType::Pwad, "pins", std::slice::from_ref(&first)),
+            Some(vec![first.clone()])
+        );
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn pinned_file_that_is_gone() {
+        let kept = temp_file("pins-kept.wad", b"PWAD");
+        let gone = std::env::temp_dir().join("playdoom-test-pins-gone.wad");
+        let mut pins = pins("pins", vec![kept.clone(), gone.clone()]);
+        assert!(pins.forget_gone(&FileType::Pwad, "pins"));
+        assert!(!pins.pwads.contains_key("pins"));
+        assert_eq!(
+            pins.pinned_among(&FileType::Pwad, "pins", &[kept.clone(), gone]),
+            None
+        );
+        std::fs::remove_file(kept).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::thread::sleep;
+use std::time::Duration;
+use std::time::Instant;
+
+use log::info;
+use log::trace;
+use log::warn;
+use ron::ser::PrettyConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::engine::DoomEngineKind;
+use crate::error::Error;
+use crate::util::modified_secs;
+
+const CHECKED_FLAGS: [&str; 8] = [
+    "-complevel",
+    "-geom",
+    "-longtics",
+    "-pistolstart",
+    "-recordfromto",
+    "-shorttics",
+    "-vidmode",
+    "-viddump",
+];
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Deserialize, Serialize)]
+pub(crate) struct Capabilities {
+    pub version: Option<String>,
+    pub flags: BTreeSet<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CachedCapabilities {
+    size: u64,
+    mtime: u64,
+    capabilities: Capabilities,
+}
+
+fn output_of(binary: &Path, arg: &str) -> String {
+    let mut child = match Command::new(binary)
+        .arg(arg)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            info!("Cannot run '{}': {}", binary.to_string_lossy(), e);
+            return String::new();
+        }
+    };
+    // Read while waiting, or a long help text fills the pipe and the engine never exits.
+    let readers = [
+        child.stdout.take().map(|mut out| {
+            std::thread::spawn(move || {
+                let mut output = vec![];
+                let _ = out.read_to_end(&mut output);
+                output
+            })
+        }),
+        child.stderr.take().map(|mut err| {
+            std::thread::spawn(move || {
+                let mut output = vec![];
+                let _ = err.read_to_end(&mut output);
+                output
+            })
+        }),
+    ];
+    let started = Instant::now();
+    while let Ok(None) = child.try_wait() {
+        if started.elapsed() > PROBE_TIMEOUT {
+            warn!(
+                "'{}' did not exit after {}, so it was stopped.",
+                binary.to_string_lossy(),
+                arg
+            );
+            let _ = child.kill();
+            let _ = child.wait();
+            break;
+        }
+        sleep(Duration::from_millis(20));
+    }
+    let output = readers
+        .into_iter()
+        .flatten()
+        .flat_map(|reader| reader.join().unwrap_or_default())
+        .collect::<Vec<_>>();
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+// The argument that makes engines of `kind` print their options and exit. PrBoom+ and
+// Chocolate Doom start the game instead, and ZDoom and Eternity have no such argument.
+fn help_arg(kind: DoomEngineKind) -> Option<&'static str> {
+    match kind {
+        // dsda-doom and Woof.
+        DoomEngineKind::Boom | DoomEngineKind::MBF => Some("-help"),
+        DoomEngineKind::Vanilla | DoomEngineKind::Eternity | DoomEngineKind::ZDoom => None,
+    }
+}
+
+fn probe(binary: &Path, help_arg: &str) -> Capabilities {
+    trace!("Probing '{}'", binary.to_string_lossy());
+    let help = output_of(binary, help_arg);
+    let version_output = output_of(binary, "--version");
+    let flags = help
+        .split(|c: char| c.is_whitespace() || "[]|,;()=".contains(c))
+        .filter(|word| {
+            word.len() > 1
+                && word.starts_with('-')
+                && word[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        })
+        .map(|word| word.to_lowercase())
+        .collect();
+    let version = version_output
+        .lines()
+        .chain(help.lines())
+        .flat_map(|line| line.split_whitespace())
+        .map(|word| word.trim_start_matches(['v', 'V']))
+        .find(|word| {
+            let mut parts = word.split('.');
+            matches!(
+                (parts.next(), parts.next()),
+                (Some(major), Some(minor))
+                    if !major.is_empty()
+                        && major.bytes().all(|b| b.is_ascii_digit())
+                        && minor.starts_with(|c: char| c.is_ascii_digit())
+            )
+        })
+        .map(|word| word.to_owned());
+    Capabilities { version, flags }
+}
+
+fn read_cache(cache_path: &Path) -> Result<HashMap<PathBuf, CachedCapabilities>, Error> {
+    if !cache_path.exists() {
+        return Ok(HashMap::new());
+    }
+    ron::from_str(&String::from_utf8_lossy(
+        &std::fs::read(cache_path).map_err(Error::Io)?,
+    ))
+    .map_err(|error| Error::BadRon {
+        file: cache_path.to_owned(),
+        error,
+    })
+}
+
+fn write_cache(
+    cache_path: &Path,
+    cache: &HashMap<PathBuf, CachedCapabilities>,
+) -> Result<(), Error> {
+    std::fs::write(
+        cache_path,
+        ron::ser::to_string_pretty(cache, PrettyConfig::default())?,
+    )
+    .map_err(Error::Io)
+}
+
+// The cache only saves probing again, so a cache that cannot be used is a cache miss.
+fn capabilities(binary: &Path, help_arg: &str) -> Capabilities {
+    let cache_path = match crate::doom_dir() {
+        Ok(doom_dir) => doom_dir.join("engine_capabilities.ron"),
+        Err(e) => {
+            warn!("Engine capabilities are not cached: {}", e);
+            return probe(binary, help_arg);
+        }
+    };
+    let mut cache = read_cache(&cache_path).unwrap_or_else(|e| {
+        warn!("Ignoring the engine capabilities cache: {}", e);
+        HashMap::new()
+    });
+
+    let (size, mtime) = match std::fs::metadata(binary) {
+        Ok(metadata) => (metadata.len(), modified_secs(&metadata)),
+        Err(e) => {
+            warn!("Engine capabilities are not cached: {}", e);
+            return probe(binary, help_arg);
+        }
+    };
+    if let Some(cached) = cache.get(binary) {
+        if cached.size == size && cached.mtime == mtime {
+            return cached.capabilities.clone();
+        }
+    }
+
+    let capabilities = probe(binary, help_arg);
+    cache.insert(
+        binary.to_owned(),
+        CachedCapabilities {
+            size,
+            mtime,
+            capabilities: capabilities.clone(),
+        },
+    );
+    if let Err(e) = write_cache(&cache_path, &cache) {
+        warn!("Engine capabilities are not cached: {}", e);
+    }
+    capabilities
+}
+
+pub(crate) fn warn_unsupported<'a>(
+    engine_name: &str,
+    kind: DoomEngineKind,
+    binary: &Path,
+    args: impl Iterator<Item = &'a str>,
+) {
+    let checked = args
+        .map(|arg| arg.trim())
+        .filter(|arg| CHECKED_FLAGS.contains(arg))
+        .collect::<Vec<_>>();
+    let help_arg = match help_arg(kind) {
+        Some(help_arg) if !checked.is_empty() && binary.exists() => help_arg,
+        _ => return,
+    };
+    let capabilities = capabilities(binary, help_arg);
+    if capabilities.flags.is_empty() {
+        info!(
+            "'{}' does not list its options, so they cannot be checked.",
+            engine_name
+        );
+        return;
+    }
+    let engine = match &capabilities.version {
+        Some(version) => format!("'{}' version {}", engine_name, version),
+        None => format!("'{}'", engine_name),
+    };
+    for arg in checked {
+        if !capabilities.flags.contains(arg) {
+            warn!(
+                "{} does not mention {} in its help; this version may not support it.",
+                engine, arg
+            );
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Write;
+
+use clap::ArgMatches;
+use indoc::indoc;
+use log::trace;
+use log::warn;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::doom_dir;
+use crate::error::Error;
+use crate::ARG_SEPARATOR;
+
+const EXAMPLE_PROFILES_FILE: &str = indoc!(
+    r#"
+    {
+        // Run a profile with `playdoom @NAME`. Flags given on the command line override the
+        // profile's, and their PWADs are loaded after the profile's.
+        // "sunlust": Profile(
+            // Every field is optional.
+            // engine: Some("dsda"),
+            // iwad: Some("doom2"),
+            // pwads: ["sunlust", "sunlust-music"],
+            // extra_pwads: [],
+            // complevel: Some("mbf"),
+            // skill: Some("uv"),
+            // Any other playdoom flags.
+            // flags: ["--pistol-start"],
+        // ),
+    }
+    "#
+);
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Profile {
+    engine: Option<String>,
+    iwad: Option<String>,
+    pwads: Vec<String>,
+    extra_pwads: Vec<String>,
+    complevel: Option<String>,
+    skill: Option<String>,
+    flags: Vec<String>,
+}
+
+impl Profile {
+    fn args(&self) -> Vec<String> {
+        let separator = ARG_SEPARATOR.to_string();
+        let options = [
+            ("engine", self.engine.clone()),
+            ("iwad", self.iwad.clone()),
+            (
+                "pwads",
+                (!self.pwads.is_empty()).then(|| self.pwads.join(&separator)),
+            ),
+            (
+                "extra-pwads",
+                (!self.extra_pwads.is_empty()).then(|| self.extra_pwads.join(&separator)),
+            ),
+            ("compatibility-level", self.complevel.clone()),
+            ("skill", self.skill.clone()),
+        ];
+        options
+            .into_iter()
+            .filter_map(|(long, value)| value.map(|value| format!("--{}={}", long, value)))
+            .chain(self.flags.iter().cloned())
+            .collect()
+    }
+}
+
+fn read_profiles() -> Result<BTreeMap<String, Profile>, Error> {
+    let profiles_path = doom_dir()?.join("profiles.ron");
+    trace!("Reading profiles from {}", profiles_path.to_string_lossy());
+    if !profiles_path.exists() {
+        warn!(
+            "Profiles not found, creating template at {}.",
+            profiles_path.to_string_lossy()
+        );
+        let mut f = File::create(&profiles_path).map_err(Error::Io)?;
+        write!(f, "{}", EXAMPLE_PROFILES_FILE).map_err(Error::Io)?;
+    }
+    ron::from_str(&String::from_utf8_lossy(
+        &std::fs::read(&profiles_path).map_err(Error::Io)?,
+    ))
+    .map_err(|error| Error::BadRon {
+        file: profiles_path,
+        error,
+    })
+}
+
+pub(crate) fn requested(matches: &ArgMatches) -> Option<String> {
+    matches
+        .values_of("passthrough")?
+        .next()
+        .filter(|arg| arg.starts_with('@'))
+        .map(|arg| arg.to_owned())
+}
+
+pub(crate) fn expand(
+    requested: &str,
+    args: impl IntoIterator<Item = OsString>,
+) -> Result<Vec<OsString>, Error> {
+    let name = &requested[1..];
+    let mut profiles = read_profiles()?;
+    let profile = profiles.remove(name).ok_or_else(|| Error::NoSuchProfile {
+        name: name.to_owned(),
+        known: profiles.keys().cloned().collect::<Vec<_>>().join(", "),
+    })?;
+    let mut args = args.into_iter();
+    let mut expanded = args.next().into_iter().collect::<Vec<_>>();
+    expanded.extend(profile.args().into_iter().map(OsString::from));
+    for arg in args.by_ref() {
+        if arg == *requested {
+            break;
+        }
+        expanded.push(arg);
+    }
+    let mut passthrough = args.peekable();
+    if passthrough.peek().is_some() && !expanded.iter().any(|arg| arg == "--") {
+        expanded.push(OsString::from("--"));
+        if passthrough.peek().map(|arg| arg == "--").unwrap_or(false) {
+            passthrough.next();
+        }
+    }
+    expanded.extend(passthrough);
+    Ok(expanded)
+}
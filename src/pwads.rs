@@ -1,9 +1,15 @@
 use std::path::Path;
 use std::path::PathBuf;
 
+use log::info;
+use log::warn;
+
 use crate::error::Error;
+use crate::pins;
 use crate::search::search_file;
 use crate::search::search_file_by;
+use crate::settings::Settings;
+use crate::util::recorded_file_name;
 use crate::FileType;
 use crate::ARG_SEPARATOR;
 
@@ -32,6 +38,11 @@ impl Pwads {
         self.dehs.push(deh);
     }
 
+    pub(crate) fn contains(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        self.wads.iter().chain(self.dehs.iter()).any(|p| p == path)
+    }
+
     pub(crate) fn wads(&self) -> &[PathBuf] {
         &self.wads
     }
@@ -45,10 +56,11 @@ pub(crate) fn parse_arg_pwads(
     arg_pwads_raw: &str,
     viddump_folder_name: &mut Vec<String>,
     pwads: &mut Pwads,
+    settings: &Settings,
 ) -> Result<(), Error> {
     let mut arg_pwads = vec![];
     for pwad in arg_pwads_raw.split(ARG_SEPARATOR) {
-        let mut pwad_files = search_file_by(pwad, FileType::Pwad, |f| {
+        let mut pwad_files = search_file_by(pwad, FileType::Pwad, settings, |f| {
             f.extension()
                 .and_then(|ext| ext.to_str())
                 .map(|ext| {
@@ -58,7 +70,7 @@ pub(crate) fn parse_arg_pwads(
                 .unwrap_or(true)
         })?;
         viddump_folder_name.extend(
-            search_file(pwad, FileType::Pwad)?
+            search_file(pwad, FileType::Pwad, settings)?
                 .iter()
                 .map(|p| {
                     p.file_stem()
@@ -71,23 +83,12 @@ pub(crate) fn parse_arg_pwads(
                 })
                 .collect::<Result<Vec<_>, _>>()?,
         );
-        let i = if pwad_files.len() > 1 {
-            dialoguer::Select::new()
-                .items(
-                    pwad_files
-                        .iter()
-                        .map(|p| p.to_string_lossy())
-                        .collect::<Vec<_>>()
-                        .as_ref(),
-                )
-                .with_prompt(
-                    format!("Multiple results were found for {}. Select one.", pwad).as_str(),
-                )
-                .interact()
-                .map_err(Error::Io)?
-        } else {
-            0
-        };
+        let i = pins::select_one(
+            &FileType::Pwad,
+            pwad,
+            &pwad_files,
+            format!("Multiple results were found for {}. Select one.", pwad),
+        )?;
         arg_pwads.push(pwad_files.remove(i));
     }
     for pwad in arg_pwads {
@@ -110,24 +111,70 @@ pub(crate) fn parse_arg_pwads(
     Ok(())
 }
 
-pub(crate) fn parse_extra_pwads(extra_pwads_raw: &str, pwads: &mut Pwads) -> Result<(), Error> {
+pub(crate) fn parse_extra_pwads(
+    extra_pwads_raw: &str,
+    pwads: &mut Pwads,
+    settings: &Settings,
+) -> Result<(), Error> {
     for pwad in extra_pwads_raw.split(ARG_SEPARATOR) {
-        let mut found = search_file(pwad, FileType::Pwad)?;
-        let i = if found.len() > 1 {
-            dialoguer::Select::new()
-                .items(
-                    &found
-                        .iter()
-                        .map(|p| p.to_string_lossy())
-                        .collect::<Vec<_>>(),
-                )
-                .with_prompt("Multiple candidates were found. Select one.")
-                .interact()
-                .map_err(Error::Io)?
+        let mut found = search_file(pwad, FileType::Pwad, settings)?;
+        let i = pins::select_one(
+            &FileType::Pwad,
+            pwad,
+            &found,
+            "Multiple candidates were found. Select one.",
+        )?;
+        pwads.add_wad(found.remove(i));
+    }
+    Ok(())
+}
+
+pub(crate) fn add_recorded_pwads(
+    files: &[String],
+    dehs: &[String],
+    viddump_folder_name: &mut Vec<String>,
+    pwads: &mut Pwads,
+    settings: &Settings,
+) -> Result<(), Error> {
+    for (recorded, is_deh) in files
+        .iter()
+        .map(|f| (f, false))
+        .chain(dehs.iter().map(|d| (d, true)))
+    {
+        let path = if Path::new(recorded).is_file() {
+            Some(PathBuf::from(recorded))
         } else {
-            0
+            match search_file(recorded_file_name(recorded), FileType::Pwad, settings) {
+                Ok(found) => found.into_iter().next(),
+                Err(Error::FileNotFound(_)) => None,
+                Err(e) => return Err(e),
+            }
         };
-        pwads.add_wad(found.remove(i));
+        let path = match path {
+            Some(p) => p,
+            None => {
+                warn!(
+                    "The demo was recorded with '{}', which was not found.",
+                    recorded
+                );
+                continue;
+            }
+        };
+        if pwads.contains(&path) {
+            continue;
+        }
+        info!(
+            "Loading '{}', which the demo was recorded with",
+            path.to_string_lossy()
+        );
+        if is_deh {
+            pwads.add_deh(path);
+        } else {
+            if let Some(stem) = path.file_stem() {
+                viddump_folder_name.push(stem.to_string_lossy().into_owned());
+            }
+            pwads.add_wad(path);
+        }
     }
     Ok(())
 }
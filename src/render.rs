@@ -11,75 +11,99 @@ use std::time::Duration;
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::Input;
 use itertools::Itertools;
-use log::error;
 use log::info;
 use log::warn;
 
-use crate::cmd::CommandLine;
-use crate::cmd::Line;
+use crate::args::EngineArgs;
+use crate::args::Opt;
 use crate::error::Error;
 use crate::job::Job;
-use crate::search::search_file;
-use crate::FileType;
+use crate::launch::Launch;
+use crate::search::search_demo;
+use crate::settings::Settings;
 
 static CANCELLABLE: AtomicBool = AtomicBool::new(false);
 static PAUSED: AtomicBool = AtomicBool::new(false);
 
-pub(crate) fn collect_renderings(matches: &str, dump_dir: &Path) -> Result<Vec<Job>, Error> {
-    matches
-        .split(':')
-        .flat_map(|demo| {
-            let results = search_file(demo, FileType::Demo).unwrap_or_else(|e| {
-                error!("{}", e);
-                exit(-1);
-            });
-            if results.is_empty() {
-                error!("Failed to find demo '{}'", demo);
-                exit(-1);
-            }
-            results
-        })
-        .map(|demo_name| {
-            let video_name = if dump_dir.exists() {
-                Ok(())
-            } else {
-                create_dir_all(dump_dir).map_err(Error::Io)
-            }
-            .and_then(|_| {
-                demo_name
-                    .file_stem()
-                    .ok_or_else(|| Error::NoFileStem(demo_name.to_string_lossy().into_owned()))
-            })
-            .map(|viddump_filename| {
-                dump_dir.join({
-                    let mut viddump_filename = viddump_filename.to_os_string();
-                    viddump_filename.push(".mp4");
-                    viddump_filename
-                })
-            });
-            video_name.map(|video_name| -> Result<Job, Error> {
-                Ok(Job {
-                    name: demo_name
-                        .file_stem()
-                        .ok_or_else(|| Error::NoFileStem(demo_name.to_string_lossy().into_owned()))?
-                        .to_str()
-                        .unwrap()
-                        .to_string(),
-                    video_name,
-                    demo_name,
-                })
-            })?
-        })
-        .collect::<Result<Vec<_>, _>>()
+pub(crate) fn find_demos(matches: &str, settings: &Settings) -> Result<Vec<PathBuf>, Error> {
+    let mut demos = vec![];
+    for demo in matches.split(':') {
+        let results = search_demo(demo, settings)?;
+        if results.is_empty() {
+            return Err(Error::FileNotFound(demo.to_owned()));
+        }
+        demos.extend(results);
+    }
+    Ok(demos)
+}
+
+pub(crate) fn render_job(
+    demo_name: PathBuf,
+    launch: &Launch,
+    engine_args: &EngineArgs,
+    dump_dir: &Path,
+) -> Result<Job, Error> {
+    if !dump_dir.exists() {
+        create_dir_all(dump_dir).map_err(Error::Io)?;
+    }
+    let name = demo_name
+        .file_stem()
+        .ok_or_else(|| Error::NoFileStem(demo_name.to_string_lossy().into_owned()))?
+        .to_owned();
+    let video_name = dump_dir.join({
+        let mut video_name = name.clone();
+        video_name.push(".mp4");
+        video_name
+    });
+    let mut launch = launch.clone();
+    engine_args.push(
+        &mut launch.cmdline,
+        Opt::TimeDemo,
+        &[(
+            "value",
+            demo_name
+                .to_str()
+                .ok_or_else(|| Error::NonUtf8Path(demo_name.to_string_lossy().into_owned()))?,
+        )],
+    )?;
+    engine_args.push(
+        &mut launch.cmdline,
+        Opt::VidDump,
+        &[(
+            "value",
+            video_name
+                .to_str()
+                .ok_or_else(|| Error::NonUtf8Path(video_name.to_string_lossy().into_owned()))?,
+        )],
+    )?;
+    Ok(Job {
+        name: name
+            .to_str()
+            .ok_or_else(|| Error::NonUtf8Path(name.to_string_lossy().into_owned()))?
+            .to_owned(),
+        demo_name,
+        launch,
+    })
+}
+
+fn goodbye() -> ! {
+    println!();
+    println!("Received interrupt, exiting. Goodbye.");
+    exit(0);
 }
 
 pub(crate) fn batch_render(
-    mut renderings: Vec<Job>,
-    cmdline: &CommandLine,
-    dump_dir: PathBuf,
+    demos: Vec<PathBuf>,
+    job_for: impl Fn(PathBuf) -> Result<Job, Error>,
+    settings: &Settings,
 ) -> Result<(), crate::error::Error> {
-    let (job_sender, job_receiver) = channel::<Result<Job, Error>>();
+    let mut renderings = demos
+        .into_iter()
+        .map(&job_for)
+        .collect::<Result<Vec<_>, _>>()?;
+    let (demo_sender, demo_receiver) = channel::<Result<PathBuf, Error>>();
     let (unpause_sender, unpause_receiver) = channel::<()>();
+    let search_settings = settings.clone();
     ctrlc::set_handler(move || {
         if CANCELLABLE.load(Ordering::Relaxed) {
             PAUSED.store(true, Ordering::SeqCst);
@@ -88,7 +112,7 @@ pub(crate) fn batch_render(
                 .allow_empty(true)
                 .interact_text()
                 .unwrap_or_else(|e| {
-                    job_sender.send(Err(Error::Io(e))).unwrap();
+                    demo_sender.send(Err(Error::Io(e))).unwrap();
                     String::new()
                 });
 
@@ -96,59 +120,27 @@ pub(crate) fn batch_render(
                 warn!("You didn't enter any demo names.");
                 return;
             }
-            let jobs_sending_result = extra_demos
+            let sent = extra_demos
                 .split_whitespace()
-                .map(|d| search_file(d, FileType::Demo))
+                .map(|demo| search_demo(demo, &search_settings))
                 .collect::<Result<_, _>>()
                 .and_then(|d: Vec<_>| {
                     d.into_iter().flatten().try_for_each(|demo_name| {
-                        let name = demo_name
-                            .file_stem()
-                            .ok_or_else(|| {
-                                Error::NoFileStem(demo_name.to_string_lossy().into_owned())
-                            })
-                            .map(|name| name.to_owned());
-                        name.and_then(|name| {
-                            let video_name = dump_dir.join({
-                                let mut name = name.clone();
-                                name.push(".mp4");
-                                name
-                            });
-                            job_sender
-                                .send(
-                                    name.to_str()
-                                        .ok_or_else(|| {
-                                            Error::NonUtf8Path(
-                                                name.as_os_str().to_string_lossy().into_owned(),
-                                            )
-                                        })
-                                        .map(|name| Job {
-                                            name: name.to_owned(),
-                                            demo_name: demo_name.clone(),
-                                            video_name,
-                                        }),
-                                )
-                                .map_err(|e| Error::Send(Box::new(e)))
-                        })
+                        demo_sender
+                            .send(Ok(demo_name))
+                            .map_err(|e| Error::Send(Box::new(e)))
                     })
                 });
-
-            if jobs_sending_result.is_err() {
-                job_sender
-                    .send(jobs_sending_result.map(|_| Job {
-                        name: String::new(),
-                        demo_name: PathBuf::new(),
-                        video_name: PathBuf::new(),
-                    }))
-                    .unwrap_or_else(|e| job_sender.send(Err(Error::Send(Box::new(e)))).unwrap());
+            if let Err(e) = sent {
+                demo_sender
+                    .send(Err(e))
+                    .unwrap_or_else(|e| demo_sender.send(Err(Error::Send(Box::new(e)))).unwrap());
             }
 
             PAUSED.store(false, Ordering::SeqCst);
             unpause_sender.send(()).unwrap();
         } else {
-            println!();
-            println!("Received interrupt, exiting. Goodbye.");
-            exit(0);
+            goodbye();
         }
     })
     .map_err(Error::SignalHandler)?;
@@ -167,29 +159,15 @@ pub(crate) fn batch_render(
         info!("==== END RENDERING QUEUE ====");
 
         let job = renderings.remove(0);
-        let render_cmdline = {
-            let mut rcmdline = cmdline.clone();
-            rcmdline.push_line(Line::from_word("-timedemo", 1));
-            rcmdline.push_line(Line::from_word(
-                job.demo_name.to_str().ok_or_else(|| {
-                    Error::NonUtf8Path(job.demo_name.to_string_lossy().into_owned())
-                })?,
-                2,
-            ));
-
-            rcmdline.push_line(Line::from_word("-viddump", 1));
-            rcmdline.push_line(Line::from_word(
-                job.video_name.to_str().ok_or_else(|| {
-                    Error::NonUtf8Path(job.video_name.to_string_lossy().into_owned())
-                })?,
-                2,
-            ));
-            rcmdline
-        };
+        let render_launch = &job.launch;
+        for line in render_launch.describe_environment() {
+            println!("{}", line);
+        }
         println!(
             "Command line #{}: \n'\n{}\n'",
             i,
-            render_cmdline
+            render_launch
+                .cmdline
                 .iter_lines()
                 .map(|l| l.iter().join(" "))
                 .join("\n")
@@ -211,12 +189,22 @@ pub(crate) fn batch_render(
                 unpause_receiver.recv()?;
             }
             CANCELLABLE.store(false, Ordering::SeqCst);
-            for job in job_receiver.try_iter() {
-                renderings.push(job?);
+            for demo in demo_receiver.try_iter() {
+                renderings.push(job_for(demo?)?);
             }
         }
 
-        crate::run_doom(render_cmdline.iter_words())?;
+        let rendered = render_launch.run(false, settings);
+        // While the engine runs, Ctrl-C reaches it rather than the handler above.
+        #[cfg(unix)]
+        if let Err(Error::EngineKilled {
+            signal: libc::SIGINT,
+            ..
+        }) = rendered
+        {
+            goodbye();
+        }
+        rendered?;
 
         i += 1;
     }
@@ -2,20 +2,21 @@ use itertools::Itertools;
 
 use crate::error::Error;
 
+use std::path::Path;
 use std::path::PathBuf;
 
 pub(crate) fn score_entry(
-    entry: &walkdir::DirEntry,
+    path: &Path,
+    is_dir: bool,
     base_name: &std::ffi::OsStr,
     extension: Option<&std::ffi::OsStr>,
     entry_extension: &str,
     ancestors: &[PathBuf],
 ) -> Result<usize, Error> {
     let mut score = 0;
-    let stem = entry
-        .path()
+    let stem = path
         .file_stem()
-        .ok_or_else(|| Error::NoFileStem(entry.path().to_string_lossy().into_owned()))?;
+        .ok_or_else(|| Error::NoFileStem(path.to_string_lossy().into_owned()))?;
     let stems_eq = stem
         .to_string_lossy()
         .eq_ignore_ascii_case(base_name.to_string_lossy().as_ref());
@@ -23,10 +24,7 @@ pub(crate) fn score_entry(
     let extensions_match = extension
         .map(|ext| ext.to_string_lossy().eq_ignore_ascii_case(entry_extension))
         .unwrap_or(true);
-    let ancestors_eq = ancestors
-        .iter()
-        .zip(entry.path().ancestors().skip(1))
-        .all_equal();
+    let ancestors_eq = ancestors.iter().zip(path.ancestors().skip(1)).all_equal();
     if stems_eq {
         // doom2
         score += 2;
@@ -46,7 +44,7 @@ pub(crate) fn score_entry(
             score += 5;
         }
     }
-    if entry.path().is_dir() {
+    if is_dir {
         // break ties with dirs and wads
         score /= 2;
     }
@@ -1,6 +1,9 @@
 use crate::error::Error;
+use crate::index;
 use crate::score::score_entry;
-use crate::util::absolute_path;
+use crate::settings::SearchPaths;
+use crate::settings::SearchRoot;
+use crate::settings::Settings;
 use crate::FileType;
 use itertools::Itertools;
 use log::info;
@@ -8,12 +11,15 @@ use log::trace;
 use std::borrow::Cow;
 use std::path::Path;
 use std::path::PathBuf;
-use walkdir::WalkDir;
 
-pub(crate) fn search_files(list: &[String], ty: FileType) -> Result<Vec<PathBuf>, Error> {
+pub(crate) fn search_files(
+    list: &[String],
+    ty: FileType,
+    settings: &Settings,
+) -> Result<Vec<PathBuf>, Error> {
     list.iter()
         .map(move |i| {
-            search_file_in_dirs_by(PathBuf::from(i), ty.get_search_dirs()?, |p| {
+            search_file_in_dirs_by(PathBuf::from(i), settings.search_paths(&ty)?, |p| {
                 ["wad", "deh", "bex", "pk3", "pk7", "pke", "zip"].contains(
                     &p.extension()
                         .map(|ext| ext.to_string_lossy())
@@ -26,117 +32,139 @@ pub(crate) fn search_files(list: &[String], ty: FileType) -> Result<Vec<PathBuf>
         .collect()
 }
 
-pub(crate) fn search_file(name: impl AsRef<str>, ty: FileType) -> Result<Vec<PathBuf>, Error> {
-    search_file_in_dirs_by(name.as_ref().into(), ty.get_search_dirs()?, |_| true)
+pub(crate) fn search_file(
+    name: impl AsRef<str>,
+    ty: FileType,
+    settings: &Settings,
+) -> Result<Vec<PathBuf>, Error> {
+    search_file_in_dirs_by(name.as_ref().into(), settings.search_paths(&ty)?, |_| true)
+}
+
+pub(crate) fn search_demo(
+    name: impl AsRef<str>,
+    settings: &Settings,
+) -> Result<Vec<PathBuf>, Error> {
+    search_file_by(name, FileType::Demo, settings, |p| {
+        !p.extension()
+            .map(|ext| ext.eq_ignore_ascii_case("ron"))
+            .unwrap_or(false)
+    })
 }
 
 pub(crate) fn search_file_by(
     name: impl AsRef<str>,
     ty: FileType,
+    settings: &Settings,
     predicate: impl Fn(&Path) -> bool,
 ) -> Result<Vec<PathBuf>, Error> {
-    search_file_in_dirs_by(name.as_ref().into(), ty.get_search_dirs()?, predicate)
+    search_file_in_dirs_by(name.as_ref().into(), settings.search_paths(&ty)?, predicate)
 }
 
 pub(crate) fn search_file_in_dirs_by(
     name: PathBuf,
-    search_dirs: Vec<PathBuf>,
+    search_paths: SearchPaths,
     predicate: impl Fn(&Path) -> bool,
 ) -> Result<Vec<PathBuf>, Error> {
     if name.is_absolute() {
         let mut parent = name.clone();
         parent.pop();
-        search_file_in_dirs_by(
+        // A path given by hand is looked up directly rather than added to the index.
+        search_roots(
             PathBuf::from(
                 name.file_stem()
                     .ok_or_else(|| Error::NoFileStem(name.to_string_lossy().into_owned()))?,
             ),
-            vec![parent],
+            SearchPaths {
+                roots: vec![SearchRoot::new(parent)],
+                extensions: None,
+            },
             predicate,
+            false,
         )
     } else {
-        for search_dir in search_dirs {
-            info!(
-                "Searching for '{}' in '{}'",
-                name.to_string_lossy(),
-                search_dir.to_string_lossy()
-            );
-
-            let base_name = name
-                .file_stem()
-                .ok_or_else(|| Error::NoFileStem(name.to_string_lossy().into_owned()))?;
-            let extension = name.extension();
-            let ancestors = name
-                .ancestors()
-                .skip(1)
-                .map(|p| p.to_path_buf())
-                .collect_vec();
+        search_roots(name, search_paths, predicate, true)
+    }
+}
 
-            let search_dir = absolute_path(PathBuf::from(&search_dir))?;
+fn search_roots(
+    name: PathBuf,
+    search_paths: SearchPaths,
+    predicate: impl Fn(&Path) -> bool,
+    indexed: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    for root in &search_paths.roots {
+        info!(
+            "Searching for '{}' in '{}'",
+            name.to_string_lossy(),
+            root.path.to_string_lossy()
+        );
 
-            struct SearchResult {
-                path: PathBuf,
-                score: usize,
-            }
-            let mut results = Vec::<SearchResult>::new();
+        let base_name = name
+            .file_stem()
+            .ok_or_else(|| Error::NoFileStem(name.to_string_lossy().into_owned()))?;
+        let extension = name.extension();
+        let ancestors = name
+            .ancestors()
+            .skip(1)
+            .map(|p| p.to_path_buf())
+            .collect_vec();
 
-            for entry in WalkDir::new(search_dir).follow_links(true) {
-                let entry = match entry {
-                    Ok(e) => e,
-                    Err(e) => {
-                        if let Some(io) = e.io_error() {
-                            if io.kind() == std::io::ErrorKind::PermissionDenied
-                                || io.kind() == std::io::ErrorKind::NotFound
-                            {
-                                continue;
-                            }
-                        }
-                        info!("Stopping search due to an error: {}", e);
-                        break;
-                    }
-                };
+        struct SearchResult {
+            path: PathBuf,
+            score: usize,
+        }
+        let mut results = Vec::<SearchResult>::new();
 
-                if !predicate(entry.path()) {
-                    continue;
-                }
+        let entries = if indexed {
+            index::entries_under(root)?
+        } else {
+            index::entries_unindexed(root)?
+        };
+        for (path, is_dir) in entries {
+            if !predicate(&path) || !search_paths.allows(&path, is_dir) {
+                continue;
+            }
 
-                let entry_extension = entry
-                    .path()
-                    .extension()
-                    .map(|e| {
-                        e.to_str().ok_or_else(|| {
-                            Error::NonUtf8Path(entry.path().to_string_lossy().into_owned())
-                        })
-                    })
-                    .transpose()?
-                    .unwrap_or("");
+            let entry_extension = path
+                .extension()
+                .map(|e| {
+                    e.to_str()
+                        .ok_or_else(|| Error::NonUtf8Path(path.to_string_lossy().into_owned()))
+                })
+                .transpose()?
+                .unwrap_or("");
 
-                let entry_score =
-                    score_entry(&entry, base_name, extension, entry_extension, &ancestors)?;
-                if (results.is_empty() && entry_score > 1)
-                    || (!results.is_empty() && entry_score > results[0].score)
-                {
-                    results.clear();
-                    results.push(SearchResult {
-                        path: entry.path().into(),
-                        score: entry_score,
-                    });
-                }
+            let entry_score = score_entry(
+                &path,
+                is_dir,
+                base_name,
+                extension,
+                entry_extension,
+                &ancestors,
+            )?;
+            if (results.is_empty() && entry_score > 1)
+                || (!results.is_empty() && entry_score > results[0].score)
+            {
+                results.clear();
+                results.push(SearchResult {
+                    path,
+                    score: entry_score,
+                });
             }
+        }
 
-            if !results.is_empty() {
-                let results = results.into_iter().map(|r| r.path).collect_vec();
-                trace!(
-                    "Results: [{}]",
-                    results
-                        .iter()
-                        .map(|r| r.to_string_lossy())
-                        .collect_vec()
-                        .join(", ")
-                );
-                return Ok(results);
-            }
+        if !results.is_empty() {
+            let results = results.into_iter().map(|r| r.path).collect_vec();
+            trace!(
+                "Results: [{}]",
+                results
+                    .iter()
+                    .map(|r| r.to_string_lossy())
+                    .collect_vec()
+                    .join(", ")
+            );
+            return Ok(results);
         }
-        Err(Error::FileNotFound(name.to_string_lossy().into_owned()))
     }
+    Err(Error::FileNotFound(name.to_string_lossy().into_owned()))
 }
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use indoc::indoc;
+use log::trace;
+use log::warn;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::debugger::Debugger;
+use crate::doom_dir;
+use crate::error::Error;
+use crate::public_doom_dir;
+use crate::FileType;
+
+const EXAMPLE_SETTINGS_FILE: &str = indoc!(
+    r#"
+    Settings(
+        // Where to look for each type of file, in order. A type that is left out is searched
+        // for in your Doom directory and then in /public/doom.
+        // iwads: Some(SearchPaths(
+            // Relative paths are relative to your Doom directory.
+            // roots: [
+            //     SearchRoot(path: "/mnt/share/iwads", max_depth: Some(1)),
+            // ],
+            // Only files with these extensions are found. Optional, defaults to any extension.
+            // extensions: Some(["wad"]),
+        // )),
+        // pwads: Some(SearchPaths(
+            // roots: [
+            //     SearchRoot(path: "library"),
+            // ],
+            // extensions: Some(["wad", "deh", "bex", "pk3", "zip"]),
+        // )),
+        // demos: Some(SearchPaths(
+            // roots: [
+            //     SearchRoot(path: "demo"),
+            // ],
+        // )),
+        // Which skill to warp with when -s is not given. Engines can override it in engines.ron.
+        // A number from 1 to 5, or itytd, hntr, hmp, uv or nm. Defaults to UV.
+        // default_skill: Some("uv"),
+        // Debuggers for --debug=NAME, on top of the built-in lldb, gdb, rr and valgrind.
+        // debuggers: {
+            // "gdb": Debugger(
+                // The words before the engine binary.
+                // command: ["gdb", "--args"],
+                // A word between the engine binary and its arguments, like lldb's "--".
+                // separator: None,
+                // What --debug-batch runs instead: it should run the engine, print a backtrace
+                // if it crashes and exit.
+                // batch_command: Some(["gdb", "-batch", "-ex", "run", "-ex", "bt", "--args"]),
+                // Output that means the engine crashed, which is then saved to logs/.
+                // crash_marker: Some("received signal"),
+            // ),
+        // },
+        // The debugger --debug uses when not given one. Defaults to the first installed one.
+        // default_debugger: Some("gdb"),
+        // How many session logs to keep in logs/. Logs of crashes are kept regardless.
+        // Defaults to 20.
+        // keep_logs: Some(20),
+    )
+    "#
+);
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    iwads: Option<SearchPaths>,
+    pwads: Option<SearchPaths>,
+    demos: Option<SearchPaths>,
+    pub default_skill: Option<String>,
+    pub debuggers: BTreeMap<String, Debugger>,
+    pub default_debugger: Option<String>,
+    pub keep_logs: Option<usize>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub(crate) struct SearchPaths {
+    pub roots: Vec<SearchRoot>,
+    #[serde(default)]
+    pub extensions: Option<Vec<String>>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub(crate) struct SearchRoot {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+}
+
+impl SearchRoot {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_depth: None,
+        }
+    }
+}
+
+impl SearchPaths {
+    // Directories are always allowed, as some engines load them like archives.
+    pub fn allows(&self, path: &Path, is_dir: bool) -> bool {
+        let extensions = match &self.extensions {
+            Some(extensions) if !is_dir => extensions,
+            _ => return true,
+        };
+        path.extension()
+            .map(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+            .unwrap_or(false)
+    }
+
+    fn push_root(&mut self, root: SearchRoot) {
+        if !self.roots.iter().any(|r| r.path == root.path) {
+            self.roots.push(root);
+        }
+    }
+}
+
+impl Settings {
+    pub fn read() -> Result<Self, Error> {
+        let settings_path = doom_dir()?.join("settings.ron");
+        trace!("Reading settings from {}", settings_path.to_string_lossy());
+        if !settings_path.exists() {
+            warn!(
+                "Settings not found, creating template at {}.",
+                settings_path.to_string_lossy()
+            );
+            let mut f = File::create(&settings_path).map_err(Error::Io)?;
+            write!(f, "{}", EXAMPLE_SETTINGS_FILE).map_err(Error::Io)?;
+        }
+        ron::from_str(&String::from_utf8_lossy(
+            &std::fs::read(&settings_path).map_err(Error::Io)?,
+        ))
+        .map_err(|error| Error::BadRon {
+            file: settings_path,
+            error,
+        })
+    }
+
+    pub fn search_paths(&self, ty: &FileType) -> Result<SearchPaths, Error> {
+        let configured = match ty {
+            FileType::Iwad => &self.iwads,
+            FileType::Pwad => &self.pwads,
+            FileType::Demo => &self.demos,
+        };
+        let mut paths = match configured {
+            Some(paths) => paths.clone(),
+            None => SearchPaths {
+                roots: vec![
+                    SearchRoot::new(doom_dir()?),
+                    SearchRoot::new(public_doom_dir()),
+                ],
+                extensions: None,
+            },
+        };
+        let doom_dir = doom_dir()?;
+        for root in paths.roots.iter_mut() {
+            if root.path.is_relative() {
+                root.path = doom_dir.join(&root.path);
+            }
+        }
+        if matches!(ty, FileType::Iwad | FileType::Pwad) {
+            let env_roots = std::env::var_os("DOOMWADDIR")
+                .map(PathBuf::from)
+                .into_iter()
+                .chain(
+                    std::env::var_os("DOOMWADPATH")
+                        .map(|p| std::env::split_paths(&p).collect::<Vec<_>>())
+                        .unwrap_or_default(),
+                )
+                .filter(|p| p.is_absolute())
+                .collect::<Vec<_>>();
+            for root in env_roots {
+                paths.push_root(SearchRoot::new(root));
+            }
+        }
+        Ok(paths)
+    }
+}
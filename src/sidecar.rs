@@ -0,0 +1,84 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use log::info;
+use ron::ser::PrettyConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::Error;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Sidecar {
+    pub engine: String,
+    pub iwad: PathBuf,
+    #[serde(default)]
+    pub pwads: Vec<PathBuf>,
+    #[serde(default)]
+    pub dehs: Vec<PathBuf>,
+    #[serde(default)]
+    pub complevel: Option<String>,
+}
+
+// Engines add `.lmp` themselves, so `run` shares the sidecar of `run.lmp`.
+pub(crate) fn sidecar_path(demo: &Path) -> PathBuf {
+    let mut name = demo.file_name().unwrap_or_default().to_os_string();
+    let is_lmp = demo
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("lmp"))
+        .unwrap_or(false);
+    if !is_lmp {
+        name.push(".lmp");
+    }
+    name.push(".ron");
+    demo.with_file_name(name)
+}
+
+impl Sidecar {
+    pub fn read_for(demo: &Path) -> Result<Option<Self>, Error> {
+        let path = sidecar_path(demo);
+        if !path.exists() {
+            return Ok(None);
+        }
+        info!("Reading launch details from '{}'", path.to_string_lossy());
+        ron::from_str(&String::from_utf8_lossy(
+            &std::fs::read(&path).map_err(Error::Io)?,
+        ))
+        .map(Some)
+        .map_err(|error| Error::BadRon { file: path, error })
+    }
+
+    pub fn write_for(&self, demo: &Path) -> Result<(), Error> {
+        let path = sidecar_path(demo);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(Error::Io)?;
+        }
+        info!("Writing launch details to '{}'", path.to_string_lossy());
+        std::fs::write(
+            &path,
+            ron::ser::to_string_pretty(self, PrettyConfig::default())?,
+        )
+        .map_err(Error::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecar_paths_keep_the_whole_name() {
+        assert_eq!(
+            sidecar_path(Path::new("/d/run")),
+            Path::new("/d/run.lmp.ron")
+        );
+        assert_eq!(
+            sidecar_path(Path::new("/d/run.lmp")),
+            Path::new("/d/run.lmp.ron")
+        );
+        assert_ne!(
+            sidecar_path(Path::new("e1m1.uv.lmp")),
+            sidecar_path(Path::new("e1m1.uv.nm.lmp"))
+        );
+    }
+}
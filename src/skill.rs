@@ -0,0 +1,36 @@
+use crate::engine::DoomEngineKind;
+use crate::error::Error;
+
+const DEFAULT_SKILL: u32 = 4;
+
+const SKILL_NAMES: [(&str, u32); 5] = [("itytd", 1), ("hntr", 2), ("hmp", 3), ("uv", 4), ("nm", 5)];
+
+pub(crate) fn resolve_skill(requested: &str) -> Result<u32, Error> {
+    let lowercase = requested.to_lowercase();
+    match lowercase.parse::<u32>() {
+        Ok(skill) if (1..=5).contains(&skill) => Some(skill),
+        Ok(_) => None,
+        Err(_) => SKILL_NAMES
+            .iter()
+            .find(|(name, _)| *name == lowercase)
+            .map(|(_, skill)| *skill),
+    }
+    .ok_or_else(|| Error::BadSkill(requested.to_owned()))
+}
+
+pub(crate) fn default_skill(engine: Option<&str>, settings: Option<&str>) -> Result<u32, Error> {
+    engine
+        .or(settings)
+        .map(resolve_skill)
+        .unwrap_or(Ok(DEFAULT_SKILL))
+}
+
+pub(crate) fn skill_values(skill: u32, kind: DoomEngineKind) -> Vec<(&'static str, String)> {
+    let value = if kind == DoomEngineKind::ZDoom {
+        // ZDoom counts skills from 0.
+        skill - 1
+    } else {
+        skill
+    };
+    vec![("value", value.to_string())]
+}
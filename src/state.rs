@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use log::info;
+use ron::ser::PrettyConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::Error;
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct LauncherState {
+    pub doom_dir: Option<PathBuf>,
+}
+
+fn state_path() -> Result<PathBuf, Error> {
+    dirs::config_dir()
+        .map(|d| d.join("playdoom").join("state.ron"))
+        .ok_or(Error::Homeless)
+}
+
+impl LauncherState {
+    pub fn read() -> Result<Self, Error> {
+        let path = state_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        ron::from_str(&String::from_utf8_lossy(
+            &std::fs::read(&path).map_err(Error::Io)?,
+        ))
+        .map_err(|error| Error::BadRon { file: path, error })
+    }
+
+    pub fn write(&self) -> Result<(), Error> {
+        let path = state_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(Error::Io)?;
+        }
+        info!("Remembering preferences in '{}'", path.to_string_lossy());
+        std::fs::write(
+            &path,
+            ron::ser::to_string_pretty(self, PrettyConfig::default())?,
+        )
+        .map_err(Error::Io)
+    }
+}
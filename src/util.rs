@@ -1,6 +1,8 @@
 use normpath::PathExt;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use crate::doom_dir;
 use crate::error::Error;
@@ -47,3 +49,68 @@ pub(crate) fn absolute_path(path: impl AsRef<Path>) -> Result<PathBuf, Error> {
         Ok(absolute_path)
     }
 }
+
+pub(crate) fn find_in_path(program: &Path) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+pub(crate) fn recorded_file_name(recorded: &str) -> &str {
+    recorded.rsplit(['/', '\\']).next().unwrap_or(recorded)
+}
+
+pub(crate) fn modified_secs(metadata: &std::fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub(crate) fn format_utc(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let (hour, minute) = (secs % 86400 / 3600, secs % 3600 / 60);
+    // Howard Hinnant's days-to-civil algorithm.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year, month, day, hour, minute
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_utc_dates() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00");
+        assert_eq!(format_utc(951_782_400), "2000-02-29 00:00");
+        assert_eq!(format_utc(1_689_357_900), "2023-07-14 18:05");
+        assert_eq!(format_utc(4_107_542_399), "2100-02-28 23:59");
+    }
+
+    #[test]
+    fn recorded_file_names() {
+        assert_eq!(recorded_file_name("C:\\doom\\e1m1.lmp"), "e1m1.lmp");
+        assert_eq!(recorded_file_name("/home/doom/e1m1.lmp"), "e1m1.lmp");
+        assert_eq!(recorded_file_name("e1m1.lmp"), "e1m1.lmp");
+    }
+}
@@ -0,0 +1,344 @@
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::choose_between;
+use crate::error::Error;
+use crate::search::search_file;
+use crate::settings::Settings;
+use crate::FileType;
+
+const HEADER_SIZE: usize = 12;
+const DIRECTORY_ENTRY_SIZE: usize = 16;
+
+const MAP_DATA_LUMPS: [&str; 2] = ["THINGS", "TEXTMAP"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WadKind {
+    Iwad,
+    Pwad,
+}
+
+impl std::fmt::Display for WadKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WadKind::Iwad => write!(f, "IWAD"),
+            WadKind::Pwad => write!(f, "PWAD"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Lump {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Wad {
+    pub path: PathBuf,
+    pub kind: WadKind,
+    lumps: Vec<Lump>,
+}
+
+fn bad_wad(path: &Path, reason: impl Into<String>) -> Error {
+    Error::BadWad {
+        file: path.to_owned(),
+        reason: reason.into(),
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn lump_name(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take_while(|b| **b != 0)
+        .map(|b| b.to_ascii_uppercase() as char)
+        .collect()
+}
+
+pub(crate) fn lumps_in(data: &[u8]) -> Option<Vec<(String, &[u8])>> {
+    let header = data.get(0..HEADER_SIZE)?;
+    if &header[0..4] != b"IWAD" && &header[0..4] != b"PWAD" {
+        return None;
+    }
+    let num_lumps = read_u32(&header[4..8]) as usize;
+    let directory_offset = read_u32(&header[8..12]) as usize;
+    let directory_end =
+        directory_offset.checked_add(num_lumps.checked_mul(DIRECTORY_ENTRY_SIZE)?)?;
+    let directory = data.get(directory_offset..directory_end)?;
+    directory
+        .chunks_exact(DIRECTORY_ENTRY_SIZE)
+        .map(|entry| {
+            let offset = read_u32(&entry[0..4]) as usize;
+            let size = read_u32(&entry[4..8]) as usize;
+            data.get(offset..offset.checked_add(size)?)
+                .map(|lump| (lump_name(&entry[8..16]), lump))
+        })
+        .collect()
+}
+
+impl Wad {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut f = File::open(path).map_err(Error::OpeningFile)?;
+
+        let mut header = [0; HEADER_SIZE];
+        f.read_exact(&mut header)
+            .map_err(|_| bad_wad(path, "file is too short to hold a header"))?;
+        let kind = match &header[0..4] {
+            b"IWAD" => WadKind::Iwad,
+            b"PWAD" => WadKind::Pwad,
+            _ => return Err(bad_wad(path, "missing IWAD/PWAD magic")),
+        };
+        let num_lumps = read_u32(&header[4..8]) as usize;
+        let directory_offset = read_u32(&header[8..12]) as u64;
+
+        let file_len = f.metadata().map_err(Error::Io)?.len();
+        let directory_len = num_lumps as u64 * DIRECTORY_ENTRY_SIZE as u64;
+        if directory_offset + directory_len > file_len {
+            return Err(bad_wad(
+                path,
+                "lump directory extends past the end of the file",
+            ));
+        }
+
+        let mut directory = vec![0; directory_len as usize];
+        f.seek(SeekFrom::Start(directory_offset))
+            .and_then(|_| f.read_exact(&mut directory))
+            .map_err(Error::Io)?;
+
+        let lumps = directory
+            .chunks_exact(DIRECTORY_ENTRY_SIZE)
+            .map(|entry| Lump {
+                offset: read_u32(&entry[0..4]),
+                size: read_u32(&entry[4..8]),
+                name: lump_name(&entry[8..16]),
+            })
+            .collect::<Vec<_>>();
+        if let Some(lump) = lumps
+            .iter()
+            .find(|l| l.offset as u64 + l.size as u64 > file_len)
+        {
+            return Err(bad_wad(
+                path,
+                format!("lump {} extends past the end of the file", lump.name),
+            ));
+        }
+
+        Ok(Self {
+            path: path.to_owned(),
+            kind,
+            lumps,
+        })
+    }
+
+    pub fn peek_kind(path: impl AsRef<Path>) -> Result<Option<WadKind>, Error> {
+        let mut f = File::open(path.as_ref()).map_err(Error::OpeningFile)?;
+        let mut magic = [0; 4];
+        if f.read_exact(&mut magic).is_err() {
+            return Ok(None);
+        }
+        Ok(match &magic {
+            b"IWAD" => Some(WadKind::Iwad),
+            b"PWAD" => Some(WadKind::Pwad),
+            _ => None,
+        })
+    }
+
+    pub fn lumps(&self) -> &[Lump] {
+        &self.lumps
+    }
+
+    pub fn has_lump(&self, name: &str) -> bool {
+        self.lumps.iter().any(|l| l.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn maps(&self) -> Vec<String> {
+        self.lumps
+            .windows(2)
+            .filter(|pair| MAP_DATA_LUMPS.contains(&pair[1].name.as_str()))
+            .map(|pair| pair[0].name.clone())
+            .collect()
+    }
+
+    pub fn read_lump(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let lump = match self
+            .lumps
+            .iter()
+            .rev()
+            .find(|l| l.name.eq_ignore_ascii_case(name))
+        {
+            Some(l) => l,
+            None => return Ok(None),
+        };
+        let mut f = File::open(&self.path).map_err(Error::OpeningFile)?;
+        let mut data = vec![0; lump.size as usize];
+        f.seek(SeekFrom::Start(lump.offset as u64))
+            .and_then(|_| f.read_exact(&mut data))
+            .map_err(Error::Io)?;
+        Ok(Some(data))
+    }
+}
+
+pub(crate) fn inspect(file: &str, settings: &Settings) -> Result<(), Error> {
+    let paths = if Path::new(file).is_file() {
+        vec![PathBuf::from(file)]
+    } else {
+        let found = search_file(file, FileType::Pwad, settings)?;
+        if found.len() > 1 {
+            choose_between(file, &found)?
+        } else {
+            found
+        }
+    };
+
+    for path in paths {
+        let wad = Wad::open(&path)?;
+        println!(
+            "{}: {}, {} lumps",
+            wad.path.to_string_lossy(),
+            wad.kind,
+            wad.lumps().len()
+        );
+        println!("{:>6}  {:<8}  {:>10}", "#", "NAME", "SIZE");
+        for (i, lump) in wad.lumps().iter().enumerate() {
+            println!("{:>6}  {:<8}  {:>10}", i, lump.name, lump.size);
+        }
+        let maps = wad.maps();
+        if maps.is_empty() {
+            println!("No maps.");
+        } else {
+            println!("{} maps: {}", maps.len(), maps.join(", "));
+        }
+        println!();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn build_wad(magic: &[u8; 4], lumps: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = magic.to_vec();
+        let data_size: usize = lumps.iter().map(|(_, lump)| lump.len()).sum();
+        data.extend((lumps.len() as u32).to_le_bytes());
+        data.extend(((HEADER_SIZE + data_size) as u32).to_le_bytes());
+        for (_, lump) in lumps {
+            data.extend(*lump);
+        }
+        let mut offset = HEADER_SIZE;
+        for (name, lump) in lumps {
+            data.extend((offset as u32).to_le_bytes());
+            data.extend((lump.len() as u32).to_le_bytes());
+            let mut padded = [0; 8];
+            padded[..name.len()].copy_from_slice(name.as_bytes());
+            data.extend(padded);
+            offset += lump.len();
+        }
+        data
+    }
+
+    pub(crate) fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("playdoom-test-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn map_lumps(marker: &'static str) -> Vec<(&'static str, &'static [u8])> {
+        vec![(marker, b""), ("THINGS", b"things"), ("LINEDEFS", b"lines")]
+    }
+
+    #[test]
+    fn lumps_in_reads_names_and_data() {
+        let data = build_wad(b"PWAD", &[("DEHACKED", b"Thing 1"), ("endoom", b"")]);
+        let lumps = lumps_in(&data).unwrap();
+        assert_eq!(lumps.len(), 2);
+        assert_eq!(lumps[0], ("DEHACKED".to_owned(), &b"Thing 1"[..]));
+        assert_eq!(lumps[1], ("ENDOOM".to_owned(), &b""[..]));
+    }
+
+    #[test]
+    fn lumps_in_rejects_bad_wads() {
+        assert!(lumps_in(b"PWAD").is_none());
+        assert!(lumps_in(&build_wad(b"ZWAD", &[("A", b"a")])).is_none());
+        let mut truncated = build_wad(b"PWAD", &[("A", b"a")]);
+        truncated.pop();
+        assert!(lumps_in(&truncated).is_none());
+    }
+
+    #[test]
+    fn lumps_in_rejects_overflowing_offsets() {
+        let mut data = build_wad(b"PWAD", &[("A", b"a")]);
+        data[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(lumps_in(&data).is_none());
+        let mut data = build_wad(b"PWAD", &[("A", b"a")]);
+        let entry = data.len() - DIRECTORY_ENTRY_SIZE;
+        data[entry..entry + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        data[entry + 4..entry + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(lumps_in(&data).is_none());
+    }
+
+    #[test]
+    fn open_reads_directory() {
+        let path = temp_file(
+            "open.wad",
+            &build_wad(b"IWAD", &[("PLAYPAL", b"palette"), ("COLORMAP", b"map")]),
+        );
+        let wad = Wad::open(&path).unwrap();
+        assert_eq!(wad.kind, WadKind::Iwad);
+        assert_eq!(
+            wad.lumps()
+                .iter()
+                .map(|l| l.name.as_str())
+                .collect::<Vec<_>>(),
+            ["PLAYPAL", "COLORMAP"]
+        );
+        assert!(wad.has_lump("colormap"));
+        assert_eq!(wad.read_lump("PLAYPAL").unwrap().unwrap(), b"palette");
+        assert_eq!(wad.read_lump("ENDOOM").unwrap(), None);
+        assert_eq!(Wad::peek_kind(&path).unwrap(), Some(WadKind::Iwad));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn open_rejects_bad_wads() {
+        let mut truncated = build_wad(b"PWAD", &[("A", b"a")]);
+        truncated.truncate(truncated.len() - 4);
+        for (name, contents) in [
+            ("short.wad", b"PWAD".to_vec()),
+            ("magic.wad", build_wad(b"ZWAD", &[])),
+            ("truncated.wad", truncated),
+        ] {
+            let path = temp_file(name, &contents);
+            assert!(matches!(Wad::open(&path), Err(Error::BadWad { .. })));
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn maps_follow_things_and_textmap() {
+        let mut lumps = map_lumps("MAP01");
+        lumps.extend(map_lumps("E1M1"));
+        lumps.extend([
+            ("MAP02", &b""[..]),
+            ("TEXTMAP", b"namespace"),
+            ("ENDMAP", b""),
+            ("DEHACKED", b""),
+        ]);
+        let path = temp_file("maps.wad", &build_wad(b"PWAD", &lumps));
+        let wad = Wad::open(&path).unwrap();
+        assert_eq!(wad.maps(), ["MAP01", "E1M1", "MAP02"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}